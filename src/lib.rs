@@ -1,6 +1,7 @@
 use clap::ValueEnum;
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use sdl2::event::Event;
+use sdl2::keyboard::Mod;
 use sdl2::mouse::MouseButton;
 use sdl2::mouse::MouseState;
 use sdl2::pixels::Color;
@@ -16,14 +17,26 @@ use sdl2::video::Window;
 use sdl2::video::WindowContext;
 use sdl2::VideoSubsystem;
 use std::cmp;
+use std::collections::BTreeSet;
 use std::env;
+use std::fmt;
+use std::fs;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::Read;
+use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::process::Command;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+#[cfg(feature = "use-usvg")]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[cfg(feature = "use-usvg")]
-use usvg::{fontdb, TreeParsing, TreeTextToPath};
+use usvg::{fontdb, NodeExt, TreeParsing, TreeTextToPath};
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum SvgBackend {
@@ -33,902 +46,4152 @@ pub enum SvgBackend {
     UsvgWithSkia,
 }
 
-trait SvgTextureBuilder<'a> {
-    fn query_size(&self, scale: f64) -> Result<Rect, String>;
-
-    fn rasterize(
-        &self,
-        texture_creator: &'a TextureCreator<WindowContext>,
-        scale: f64,
-    ) -> Result<Texture<'a>, String>;
+/// Values of SVG `shape-rendering` property
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ShapeRendering {
+    OptimizeSpeed,
+    CrispEdges,
+    GeometricPrecision,
 }
 
-#[cfg(feature = "use-rsvg")]
-struct RsvgWithCairo {
-    handle: rsvg::SvgHandle,
+/// Values of SVG `text-rendering` property
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TextRendering {
+    OptimizeSpeed,
+    OptimizeLegibility,
+    GeometricPrecision,
 }
 
-#[cfg(feature = "use-rsvg")]
-impl RsvgWithCairo {
-    fn new<P: AsRef<Path>>(path: P) -> Result<RsvgWithCairo, String> {
-        let mut handle = rsvg::Loader::new()
-            .read_path(path)
-            .map_err(|e| e.to_string())?;
-        // TODO: crispEdges should be optional
-        handle
-            .set_stylesheet(":root { shape-rendering: crispEdges; } ")
-            .map_err(|e| e.to_string())?;
-        Ok(RsvgWithCairo { handle })
-    }
+/// Values of SVG `image-rendering` property
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ImageRendering {
+    OptimizeQuality,
+    OptimizeSpeed,
 }
 
-#[cfg(feature = "use-rsvg")]
-impl<'a> SvgTextureBuilder<'a> for RsvgWithCairo {
-    fn query_size(&self, scale: f64) -> Result<Rect, String> {
-        let size = rsvg::CairoRenderer::new(&self.handle)
-            .intrinsic_size_in_pixels()
-            .ok_or("ERROR: Could not determine SVG size in pixels")?;
-
-        let width = f64::ceil(size.0 * scale) as u32;
-        let height = f64::ceil(size.1 * scale) as u32;
-
-        Ok(Rect::new(0, 0, width, height))
-    }
-
-    fn rasterize(
-        &self,
-        texture_creator: &'a TextureCreator<WindowContext>,
-        scale: f64,
-    ) -> Result<Texture<'a>, String> {
-        let size = self.query_size(scale)?;
+/// Options applied by every backend, so images rendered by different backends
+/// can be compared. Overrides replace properties set by documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderOptions {
+    /// When disabled, shapes are rendered with `crispEdges` and text with
+    /// `optimizeSpeed` unless overridden
+    pub antialiasing: bool,
+    pub shape_rendering: Option<ShapeRendering>,
+    pub text_rendering: Option<TextRendering>,
+    pub image_rendering: Option<ImageRendering>,
+    /// CSS applied to every document as user stylesheet: its rules override
+    /// presentation attributes, while rules and `style` attributes of the document
+    /// take precedence regardless of specificity
+    pub stylesheet: Option<String>,
+    pub fonts: Fonts,
+}
 
-        let mut texture: Texture<'a> = texture_creator
-            .create_texture_streaming(
-                sdl2::pixels::PixelFormatEnum::ARGB8888,
-                size.width(),
-                size.height(),
-            )
-            .map_err(|e| e.to_string())?;
-
-        let _ = texture.with_lock(
-            None,
-            |buffer: &mut [u8], pitch: usize| -> Result<(), String> {
-                let data_ptr: *mut u8 = buffer.as_mut_ptr();
-                let surface = unsafe {
-                    cairo::ImageSurface::create_for_data_unsafe(
-                        data_ptr,
-                        cairo::Format::ARgb32,
-                        size.width() as i32,
-                        size.height() as i32,
-                        pitch as i32,
-                    )
-                }
-                .map_err(|e| e.to_string())?;
-                let cr = cairo::Context::new(&surface).map_err(|e| e.to_string())?;
-                rsvg::CairoRenderer::new(&self.handle)
-                    .render_document(
-                        &cr,
-                        &cairo::Rectangle::new(0.0, 0.0, size.width() as f64, size.height() as f64),
-                    )
-                    .map_err(|e| e.to_string())?;
-                Ok(())
-            },
-        )?;
+/// Fonts available for text rendering. Used only by usvg backend,
+/// rsvg uses fonts configured with fontconfig.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontOptions {
+    /// Fonts installed in the system, disable for renders reproducible across machines
+    pub system_fonts: bool,
+    /// Directories scanned recursively for fonts
+    pub font_dirs: Vec<PathBuf>,
+    pub font_files: Vec<PathBuf>,
+    /// Families used for generic `serif`, `sans-serif` and `monospace` families,
+    /// serif family is the default for text without `font-family`
+    pub serif_family: Option<String>,
+    pub sans_serif_family: Option<String>,
+    pub monospace_family: Option<String>,
+}
 
-        texture.set_blend_mode(sdl2::render::BlendMode::Blend);
-        Ok(texture)
+impl Default for FontOptions {
+    fn default() -> FontOptions {
+        FontOptions {
+            system_fonts: true,
+            font_dirs: Vec::new(),
+            font_files: Vec::new(),
+            serif_family: None,
+            sans_serif_family: None,
+            monospace_family: None,
+        }
     }
 }
 
-#[cfg(feature = "use-usvg")]
-struct UsvgWithSkia {
-    tree: resvg::Tree,
+/// Fonts loaded according to [`FontOptions`]. Database is loaded on first use
+/// and shared by clones, so documents rendered with the same options (for example
+/// on render thread) do not load fonts again. Create new `Fonts` to reload them.
+#[derive(Clone)]
+pub struct Fonts {
+    options: FontOptions,
+    #[cfg(feature = "use-usvg")]
+    database: Arc<Mutex<Option<Arc<fontdb::Database>>>>,
 }
 
-#[cfg(feature = "use-usvg")]
-impl UsvgWithSkia {
-    fn new<P: AsRef<Path>>(path: P) -> Result<UsvgWithSkia, String> {
-        let tree = {
-            let mut opt = usvg::Options::default();
-            // Get file's absolute directory.
-            opt.resources_dir = std::fs::canonicalize(&path)
-                .ok()
-                .and_then(|p| p.parent().map(|p| p.to_path_buf()));
+impl Fonts {
+    pub fn new(options: FontOptions) -> Fonts {
+        Fonts {
+            options,
+            #[cfg(feature = "use-usvg")]
+            database: Arc::new(Mutex::new(None)),
+        }
+    }
 
-            let mut fontdb = fontdb::Database::new();
-            fontdb.load_system_fonts();
+    pub fn options(&self) -> &FontOptions {
+        &self.options
+    }
 
-            let svg_data = std::fs::read(&path).map_err(|e| e.to_string())?;
-            let mut tree = usvg::Tree::from_data(&svg_data, &opt).map_err(|e| e.to_string())?;
-            tree.convert_text(&fontdb);
-            resvg::Tree::from_usvg(&tree)
-        };
-        Ok(UsvgWithSkia { tree })
+    #[cfg(feature = "use-usvg")]
+    fn database(&self) -> Result<Arc<fontdb::Database>, Error> {
+        let mut database = self.database.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(fontdb) = database.as_ref() {
+            return Ok(Arc::clone(fontdb));
+        }
+        let fontdb = Arc::new(load_font_database(&self.options)?);
+        *database = Some(Arc::clone(&fontdb));
+        Ok(fontdb)
     }
 }
 
-#[cfg(feature = "use-usvg")]
-impl<'a> SvgTextureBuilder<'a> for UsvgWithSkia {
-    fn query_size(&self, scale: f64) -> Result<Rect, String> {
-        let pixmap_size = self
-            .tree
-            .size
-            .to_int_size()
-            .scale_by(scale as f32)
-            .ok_or(format!("ERROR: Could not scale SVG by factor of {}", scale))?;
-        Ok(Rect::new(0, 0, pixmap_size.width(), pixmap_size.height()))
+impl Default for Fonts {
+    fn default() -> Fonts {
+        Fonts::new(FontOptions::default())
     }
+}
 
-    fn rasterize(
-        &self,
-        texture_creator: &'a TextureCreator<WindowContext>,
-        scale: f64,
-    ) -> Result<Texture<'a>, String> {
-        let size = self.query_size(scale)?;
-
-        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
-            .ok_or("ERROR: Failed to create new pixmap")?;
-        let render_ts = tiny_skia::Transform::from_scale(scale as f32, scale as f32);
-        self.tree.render(render_ts, &mut pixmap.as_mut());
-
-        let mut texture: Texture<'a> = texture_creator
-            .create_texture_streaming(
-                sdl2::pixels::PixelFormatEnum::ABGR8888,
-                size.width(),
-                size.height(),
-            )
-            .map_err(|e| e.to_string())?;
+impl fmt::Debug for Fonts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.options.fmt(f)
+    }
+}
 
-        texture.with_lock(None, |buffer: &mut [u8], _pitch: usize| {
-            buffer.copy_from_slice(pixmap.data());
-        })?;
+/// Fonts are equal only when they share the loaded database
+impl PartialEq for Fonts {
+    fn eq(&self, other: &Fonts) -> bool {
+        #[cfg(feature = "use-usvg")]
+        if !Arc::ptr_eq(&self.database, &other.database) {
+            return false;
+        }
+        self.options == other.options
+    }
+}
 
-        texture.set_blend_mode(sdl2::render::BlendMode::Blend);
-        return Ok(texture);
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            antialiasing: true,
+            shape_rendering: None,
+            text_rendering: None,
+            image_rendering: None,
+            stylesheet: None,
+            fonts: Fonts::default(),
+        }
     }
 }
 
-trait CanvasEntity {
-    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String>;
+impl RenderOptions {
+    fn shape_rendering(&self) -> Option<ShapeRendering> {
+        self.shape_rendering
+            .or((!self.antialiasing).then_some(ShapeRendering::CrispEdges))
+    }
 
-    fn size(&self) -> (u32, u32);
+    fn text_rendering(&self) -> Option<TextRendering> {
+        self.text_rendering
+            .or((!self.antialiasing).then_some(TextRendering::OptimizeSpeed))
+    }
 
-    fn reposition(&mut self, position: Point);
+    /// Logs a warning when user stylesheet uses CSS custom properties, which
+    /// neither backend supports
+    fn warn_unsupported_stylesheet(&self) {
+        let Some(stylesheet) = &self.stylesheet else {
+            return;
+        };
+        let custom_properties = stylesheet
+            .split(['{', ';'])
+            .any(|declaration| declaration.trim_start().starts_with("--"));
+        if custom_properties || stylesheet.contains("var(") {
+            warn!("CSS custom properties and var() are not supported, stylesheet declarations using them were ignored");
+        }
+    }
 
-    fn center_on(&mut self, position: Point) {
-        let size = self.size();
-        let offset = Point::new(size.0 as i32 / 2, size.1 as i32 / 2);
-        self.reposition(position - offset)
+    /// User stylesheet followed by CSS rules overriding rendering properties
+    /// of all elements
+    #[cfg(feature = "use-rsvg")]
+    fn rsvg_stylesheet(&self) -> String {
+        let mut stylesheet = self.stylesheet.clone().unwrap_or_default();
+        let mut rule = |property: &str, value: Option<&str>| {
+            if let Some(value) = value {
+                stylesheet.push_str(&format!("* {{ {}: {} !important; }} ", property, value));
+            }
+        };
+        rule(
+            "shape-rendering",
+            self.shape_rendering().map(|mode| match mode {
+                ShapeRendering::OptimizeSpeed => "optimizeSpeed",
+                ShapeRendering::CrispEdges => "crispEdges",
+                ShapeRendering::GeometricPrecision => "geometricPrecision",
+            }),
+        );
+        rule(
+            "text-rendering",
+            self.text_rendering().map(|mode| match mode {
+                TextRendering::OptimizeSpeed => "optimizeSpeed",
+                TextRendering::OptimizeLegibility => "optimizeLegibility",
+                TextRendering::GeometricPrecision => "geometricPrecision",
+            }),
+        );
+        rule(
+            "image-rendering",
+            self.image_rendering.map(|mode| match mode {
+                ImageRendering::OptimizeQuality => "optimizeQuality",
+                ImageRendering::OptimizeSpeed => "optimizeSpeed",
+            }),
+        );
+        stylesheet
+    }
+
+    /// Sets rendering modes of all nodes, including clip paths, masks and patterns.
+    /// Has to be called before text is converted to paths.
+    #[cfg(feature = "use-usvg")]
+    fn apply_to(&self, root: &usvg::Node) {
+        let shape_rendering = self.shape_rendering().map(|mode| match mode {
+            ShapeRendering::OptimizeSpeed => usvg::ShapeRendering::OptimizeSpeed,
+            ShapeRendering::CrispEdges => usvg::ShapeRendering::CrispEdges,
+            ShapeRendering::GeometricPrecision => usvg::ShapeRendering::GeometricPrecision,
+        });
+        let text_rendering = self.text_rendering().map(|mode| match mode {
+            TextRendering::OptimizeSpeed => usvg::TextRendering::OptimizeSpeed,
+            TextRendering::OptimizeLegibility => usvg::TextRendering::OptimizeLegibility,
+            TextRendering::GeometricPrecision => usvg::TextRendering::GeometricPrecision,
+        });
+        let image_rendering = self.image_rendering.map(|mode| match mode {
+            ImageRendering::OptimizeQuality => usvg::ImageRendering::OptimizeQuality,
+            ImageRendering::OptimizeSpeed => usvg::ImageRendering::OptimizeSpeed,
+        });
+
+        for node in root.descendants() {
+            match *node.borrow_mut() {
+                usvg::NodeKind::Path(ref mut path) => {
+                    path.rendering_mode = shape_rendering.unwrap_or(path.rendering_mode);
+                }
+                usvg::NodeKind::Text(ref mut text) => {
+                    text.rendering_mode = text_rendering.unwrap_or(text.rendering_mode);
+                }
+                usvg::NodeKind::Image(ref mut image) => {
+                    image.rendering_mode = image_rendering.unwrap_or(image.rendering_mode);
+                }
+                usvg::NodeKind::Group(_) => {}
+            }
+            node.subroots(|subroot| self.apply_to(&subroot));
+        }
     }
 }
 
-/// Entity of single fixed texture which is always drawn fully
-/// (without clipping, stretching etc) to given position
-struct SimpleCanvasEntity<'a> {
-    texture: Texture<'a>,
-    position: Point,
+#[derive(Debug)]
+pub enum Error {
+    /// File could not be read or written
+    Io(std::io::Error),
+    /// Document could not be parsed, `backend` names library which failed
+    Parse {
+        backend: &'static str,
+        message: String,
+    },
+    /// Document could not be rendered
+    Render(String),
+    /// Rendered image size is out of allowed range
+    SizeOutOfRange {
+        size: (u32, u32),
+        min: (u32, u32),
+        max: (u32, u32),
+    },
+    /// Window, renderer or texture failure
+    Display(String),
 }
 
-impl<'a> CanvasEntity for SimpleCanvasEntity<'a> {
-    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String> {
-        renderer.copy(
-            &self.texture,
-            None,
-            Rect::new(
-                self.position.x,
-                self.position.y,
-                self.size().0,
-                self.size().1,
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse { backend, message } => {
+                write!(f, "Could not parse file with {}: {}", backend, message)
+            }
+            Error::Render(message) => write!(f, "Could not render image: {}", message),
+            Error::SizeOutOfRange { size, min, max } => write!(
+                f,
+                "Image size {:?}px out of allowed range, minimum size {:?}px, maximum size {:?}px",
+                size, min, max
             ),
-        )
+            Error::Display(message) => write!(f, "Display error: {}", message),
+        }
     }
+}
 
-    fn size(&self) -> (u32, u32) {
-        let TextureQuery { width, height, .. } = self.texture.query();
-        (width, height)
-    }
+impl std::error::Error for Error {}
 
-    fn reposition(&mut self, position: Point) {
-        self.position = position;
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-enum Side {
-    Left,
-    Right,
+/// SDL reports errors as plain strings
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::Display(message)
+    }
 }
 
-struct SplitView<'a> {
-    texture: Texture<'a>,
-    width: u32,
-    height: u32,
-    side: Side,
-    position: Point,
-    split: u32,
+/// Rasterized image stored in memory as RGBA8888 (premultiplied alpha)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PixelBuffer {
+    pub width: u32,
+    pub height: u32,
+    /// Pixel rows without padding, 4 bytes per pixel
+    pub data: Vec<u8>,
 }
 
-impl<'a> SplitView<'a> {
-    fn new(texture: Texture<'a>, side: Side) -> SplitView<'a> {
-        let query = texture.query();
-        SplitView {
-            texture,
-            width: query.width,
-            height: query.height,
-            side,
-            position: Point::new(0, 0),
-            split: 0,
+impl PixelBuffer {
+    /// Returns pixel at given position, transparent if position is out of bounds
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        if x >= self.width || y >= self.height {
+            return [0, 0, 0, 0];
         }
+        let i = 4 * (y * self.width + x) as usize;
+        [
+            self.data[i],
+            self.data[i + 1],
+            self.data[i + 2],
+            self.data[i + 3],
+        ]
     }
+}
 
-    fn split(&mut self, split: u32) {
-        self.split = split.clamp(0, self.width)
+trait SvgTextureBuilder {
+    fn query_size(&self, scale: f64) -> Result<Rect, Error>;
+
+    /// Renders part of the scaled image, `region` is given in scaled image pixels
+    fn render_region(&self, scale: f64, region: Rect) -> Result<PixelBuffer, Error>;
+
+    fn render(&self, scale: f64) -> Result<PixelBuffer, Error> {
+        self.render_region(scale, self.query_size(scale)?)
+    }
+
+    /// Warnings reported by backend while loading the document
+    fn warnings(&self) -> &[String] {
+        &[]
     }
 }
 
-impl<'a> CanvasEntity for SplitView<'a> {
-    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String> {
-        let texture = &self.texture;
-
-        let src: Rect;
-        let dst: Rect;
-        match self.side {
-            Side::Left => {
-                src = Rect::new(0, 0, self.split, self.height);
-                dst = Rect::new(self.position.x, self.position.y, self.split, self.height);
-            }
-            Side::Right => {
-                let left_x = i32::try_from(self.split).map_err(|e| e.to_string())?;
-                src = Rect::new(left_x, 0, self.width - self.split, self.height);
-                dst = Rect::new(
-                    self.position.x + left_x,
-                    self.position.y,
-                    self.width - self.split,
-                    self.height,
-                );
-            }
-        };
+/// Warnings of left and right document
+pub type WarningPair = (Vec<String>, Vec<String>);
 
-        // Rect type has to have width greater than 0 so we should better detect
-        // case when split is out of texture and do not render corresponding part.
-        // Right now we will render 1px width slice in such scenario.
-        renderer.copy(texture, src, dst)?;
+/// Collects warnings logged by backends while documents are loaded
+mod log_module {
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use std::cell::RefCell;
+    use std::cmp;
 
-        Ok(())
+    thread_local! {
+        static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
     }
 
-    fn size(&self) -> (u32, u32) {
-        (self.width, self.height)
+    /// Runs `f` and returns its result with warnings logged meanwhile on current
+    /// thread. Warnings are collected only when [`Logger`] is installed.
+    pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+        let previous = CAPTURED.with(|c| c.replace(Some(Vec::new())));
+        let result = f();
+        let warnings = CAPTURED.with(|c| c.replace(previous)).unwrap_or_default();
+        (result, warnings)
     }
 
-    fn reposition(&mut self, position: Point) {
-        self.position = position;
+    /// Logger configured with `RUST_LOG` environment variable like `env_logger`,
+    /// which additionally collects warnings for [`capture`]
+    pub struct Logger {
+        inner: env_logger::Logger,
     }
-}
 
-struct Diff<'a> {
-    left: SplitView<'a>,
-    right: SplitView<'a>,
-    position: Point,
-    split: u32,
-}
-
-impl<'a> Diff<'a> {
-    fn new(left: Texture<'a>, right: Texture<'a>) -> Diff<'a> {
-        let mut left = SplitView::new(left, Side::Left);
-        let mut right = SplitView::new(right, Side::Right);
-        let position = Point::new(0, 0);
-        let split = cmp::min(left.width, right.width) / 2;
-        left.split(split);
-        right.split(split);
-        Diff {
-            left,
-            right,
-            position,
-            split,
+    impl Log for Logger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Warn || self.inner.enabled(metadata)
         }
-    }
 
-    fn update_split(&mut self, split: u32) {
-        self.split = split;
-        self.left.split(self.split);
-        self.right.split(self.split);
-        debug!("New split position {:?}", self.split);
-    }
+        fn log(&self, record: &Record) {
+            if record.level() <= Level::Warn {
+                let _ = CAPTURED.try_with(|c| {
+                    if let Some(warnings) = c.borrow_mut().as_mut() {
+                        warnings.push(record.args().to_string());
+                    }
+                });
+            }
+            if self.inner.matches(record) {
+                self.inner.log(record);
+            }
+        }
 
-    fn update(&mut self, state: &MouseState) {
-        if state.is_mouse_button_pressed(MouseButton::Left) {
-            let max = cmp::max(self.left.width, self.right.width);
-            let split = u32::try_from(state.x() - self.position.x())
-                .unwrap_or(0)
-                .clamp(0, max);
-            self.update_split(split);
+        fn flush(&self) {
+            self.inner.flush()
         }
     }
 
-    fn split_by_fraction(&mut self, fraction: f64) {
-        let split = (fraction.clamp(0.0, 1.0) * f64::from(self.size().0)) as u32;
-        self.update_split(split);
+    pub fn init() -> Result<(), log::SetLoggerError> {
+        let inner = env_logger::Builder::from_default_env().build();
+        log::set_max_level(cmp::max(inner.filter(), LevelFilter::Warn));
+        log::set_boxed_logger(Box::new(Logger { inner }))
     }
 
-    fn get_left_fraction(&self) -> f64 {
-        f64::from(self.split) / f64::from(self.size().0)
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use log::{info, warn};
+        use std::thread;
+
+        #[test]
+        fn test_capture() {
+            // logger might be already installed by other test
+            let _ = init();
+            warn!("not captured");
+            let ((), warnings) = capture(|| {
+                warn!("first");
+                info!("not a warning");
+                thread::spawn(|| warn!("other thread")).join().unwrap();
+                warn!("second");
+            });
+            assert_eq!(warnings, vec!["first", "second"]);
+        }
     }
 }
 
-impl<'a> CanvasEntity for Diff<'a> {
-    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String> {
-        self.left.draw(renderer)?;
-        self.right.draw(renderer)?;
+/// Installs logger which is required to collect backend warnings, use instead of
+/// `env_logger::init`
+pub fn init_logger() -> Result<(), log::SetLoggerError> {
+    log_module::init()
+}
 
-        // draw left/right separator
-        let split_x = i32::try_from(self.split).map_err(|e| e.to_string())?;
-        let height = cmp::max(self.left.height, self.right.height);
-        renderer.set_draw_color(Color::RGB(255, 0, 0));
-        renderer.fill_rect(Rect::new(
-            self.position.x + split_x,
-            self.position.y,
-            3,
-            height,
-        ))?;
+fn create_texture<'a>(
+    texture_creator: &'a TextureCreator<WindowContext>,
+    pixels: &PixelBuffer,
+) -> Result<Texture<'a>, Error> {
+    let mut texture: Texture<'a> = texture_creator
+        .create_texture_streaming(
+            sdl2::pixels::PixelFormatEnum::ABGR8888,
+            pixels.width,
+            pixels.height,
+        )
+        .map_err(|e| Error::Display(e.to_string()))?;
 
-        Ok(())
+    texture
+        .update(None, &pixels.data, 4 * pixels.width as usize)
+        .map_err(|e| Error::Display(e.to_string()))?;
+
+    texture.set_blend_mode(sdl2::render::BlendMode::Blend);
+    Ok(texture)
+}
+
+#[cfg(feature = "use-rsvg")]
+struct RsvgWithCairo {
+    handle: rsvg::SvgHandle,
+    /// Elements ignored by librsvg, its other diagnostics are printed to stdout
+    /// only with `RSVG_LOG` environment variable and can't be collected
+    warnings: Vec<String>,
+}
+
+/// Elements affecting rendering which are not supported by librsvg
+#[cfg(feature = "use-rsvg")]
+const RSVG_UNSUPPORTED_ELEMENTS: &[&str] = &[
+    "altGlyph",
+    "altGlyphDef",
+    "altGlyphItem",
+    "color-profile",
+    "font",
+    "font-face",
+    "foreignObject",
+    "glyph",
+    "glyphRef",
+    "hkern",
+    "missing-glyph",
+    "textPath",
+    "vkern",
+];
+
+/// Logs warning for every kind of element of SVG document which librsvg ignores,
+/// compressed documents are not checked
+#[cfg(feature = "use-rsvg")]
+fn warn_unsupported_elements(path: &Path) {
+    let Ok(text) = fs::read_to_string(path) else {
+        return;
+    };
+    let Ok(document) = parse_xml(&text) else {
+        return;
+    };
+    let mut reported = BTreeSet::new();
+    for node in document.descendants().filter(|n| n.is_element()) {
+        let name = node.tag_name();
+        if name.namespace() == Some("http://www.w3.org/2000/svg")
+            && RSVG_UNSUPPORTED_ELEMENTS.contains(&name.name())
+            && reported.insert(name.name())
+        {
+            warn!(
+                "'{}' elements are not supported and were ignored",
+                name.name()
+            );
+        }
     }
+}
 
-    fn size(&self) -> (u32, u32) {
-        let width = cmp::max(self.left.width, self.right.width);
-        let height = cmp::max(self.left.height, self.right.height);
-        (width, height)
+#[cfg(feature = "use-rsvg")]
+impl RsvgWithCairo {
+    fn new<P: AsRef<Path>>(path: P, options: &RenderOptions) -> Result<RsvgWithCairo, Error> {
+        let path = path.as_ref();
+        let (handle, warnings) = log_module::capture(|| -> Result<rsvg::SvgHandle, Error> {
+            let handle = RsvgWithCairo::load(path, options)?;
+            options.warn_unsupported_stylesheet();
+            warn_unsupported_elements(path);
+            Ok(handle)
+        });
+        Ok(RsvgWithCairo {
+            handle: handle?,
+            warnings,
+        })
     }
 
-    fn reposition(&mut self, position: Point) {
-        self.left.reposition(position);
-        self.right.reposition(position);
-        self.position = position;
+    fn load(path: &Path, options: &RenderOptions) -> Result<rsvg::SvgHandle, Error> {
+        let mut handle = rsvg::Loader::new().read_path(path).map_err(|e| match e {
+            rsvg::LoadingError::Io(message) => {
+                Error::Io(std::io::Error::new(std::io::ErrorKind::Other, message))
+            }
+            e => Error::Parse {
+                backend: "librsvg",
+                message: e.to_string(),
+            },
+        })?;
+        handle
+            .set_stylesheet(&options.rsvg_stylesheet())
+            .map_err(|e| Error::Parse {
+                backend: "librsvg",
+                message: e.to_string(),
+            })?;
+        Ok(handle)
     }
 }
 
-struct CheckerBoard<'a> {
-    texture: Texture<'a>,
-    width: u32,
-    height: u32,
-    position: Point,
-}
+#[cfg(feature = "use-rsvg")]
+impl SvgTextureBuilder for RsvgWithCairo {
+    fn query_size(&self, scale: f64) -> Result<Rect, Error> {
+        let size = rsvg::CairoRenderer::new(&self.handle)
+            .intrinsic_size_in_pixels()
+            .ok_or(Error::Render(String::from(
+                "could not determine SVG size in pixels",
+            )))?;
 
-impl<'a> CheckerBoard<'a> {
-    const SQUARE_SIZE: u32 = 8;
-    const WIDTH: u32 = CheckerBoard::SQUARE_SIZE * 64;
-    const HEIGHT: u32 = CheckerBoard::WIDTH;
+        let width = f64::ceil(size.0 * scale) as u32;
+        let height = f64::ceil(size.1 * scale) as u32;
 
-    fn new(
-        texture_creator: &'a TextureCreator<WindowContext>,
-        size: (u32, u32),
-    ) -> Result<CheckerBoard<'a>, String> {
-        // Create pixel data for the checkerboard pattern
-        let mut pixels =
-            Vec::with_capacity((CheckerBoard::WIDTH * CheckerBoard::HEIGHT) as usize * 4);
+        Ok(Rect::new(0, 0, width, height))
+    }
 
-        for y in 0..CheckerBoard::HEIGHT {
-            for x in 0..CheckerBoard::WIDTH {
-                let color =
-                    if (x / CheckerBoard::SQUARE_SIZE + y / CheckerBoard::SQUARE_SIZE) % 2 == 0 {
-                        Color::RGB(189, 189, 189)
-                    } else {
-                        Color::RGB(209, 209, 209)
-                    };
+    fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
 
-                let (r, g, b, a) = color.rgba();
-                pixels.push(r);
-                pixels.push(g);
-                pixels.push(b);
-                pixels.push(a);
+    fn render_region(&self, scale: f64, region: Rect) -> Result<PixelBuffer, Error> {
+        let size = self.query_size(scale)?;
+
+        let mut surface = cairo::ImageSurface::create(
+            cairo::Format::ARgb32,
+            region.width() as i32,
+            region.height() as i32,
+        )
+        .map_err(|e| Error::Render(e.to_string()))?;
+        {
+            let cr = cairo::Context::new(&surface).map_err(|e| Error::Render(e.to_string()))?;
+            cr.translate(-region.x() as f64, -region.y() as f64);
+            rsvg::CairoRenderer::new(&self.handle)
+                .render_document(
+                    &cr,
+                    &cairo::Rectangle::new(0.0, 0.0, size.width() as f64, size.height() as f64),
+                )
+                .map_err(|e| Error::Render(e.to_string()))?;
+        }
+        surface.flush();
+
+        let stride = surface.stride() as usize;
+        let surface_data = surface.data().map_err(|e| Error::Render(e.to_string()))?;
+
+        // cairo stores pixels as native-endian 32-bit ARGB words, convert to RGBA bytes
+        let mut data = Vec::with_capacity((region.width() * region.height()) as usize * 4);
+        for row in surface_data.chunks(stride).take(region.height() as usize) {
+            for px in row[..region.width() as usize * 4].chunks_exact(4) {
+                let argb = u32::from_ne_bytes([px[0], px[1], px[2], px[3]]);
+                data.extend_from_slice(&[
+                    (argb >> 16) as u8,
+                    (argb >> 8) as u8,
+                    argb as u8,
+                    (argb >> 24) as u8,
+                ]);
             }
         }
 
-        // Create a Surface and load pixel data
-        let surface = sdl2::surface::Surface::from_data(
-            &mut pixels,
-            CheckerBoard::WIDTH,
-            CheckerBoard::HEIGHT,
-            4 * CheckerBoard::WIDTH,
-            sdl2::pixels::PixelFormatEnum::ARGB8888,
-        )?;
-
-        // Convert the Surface to a Texture
-        let texture = texture_creator
-            .create_texture_from_surface(surface)
-            .map_err(|e| e.to_string())?;
-        let position = Point::new(0, 0);
-
-        Ok(CheckerBoard {
-            texture,
-            width: size.0,
-            height: size.1,
-            position,
+        Ok(PixelBuffer {
+            width: region.width(),
+            height: region.height(),
+            data,
         })
     }
-
-    fn set_size(&mut self, size: (u32, u32)) -> &mut CheckerBoard<'a> {
-        self.width = size.0;
-        self.height = size.1;
-        self
-    }
 }
 
-impl<'a> CanvasEntity for CheckerBoard<'a> {
-    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String> {
-        let mut remaining_x: u32 = self.width;
-        while remaining_x > 0 {
-            let w = cmp::min(remaining_x, CheckerBoard::WIDTH);
-
-            let mut remaining_y: u32 = self.height;
-            while remaining_y > 0 {
-                let h = cmp::min(remaining_y, CheckerBoard::HEIGHT);
+#[cfg(feature = "use-usvg")]
+struct UsvgWithSkia {
+    tree: resvg::Tree,
+    /// Unsupported features and other problems logged while loading
+    warnings: Vec<String>,
+}
 
-                let dst = Rect::new(
-                    self.position.x + (self.width - remaining_x) as i32,
-                    self.position.y + (self.height - remaining_y) as i32,
-                    w,
-                    h,
-                );
-                let src = Rect::new(0, 0, dst.width(), dst.height());
+#[cfg(feature = "use-usvg")]
+impl UsvgWithSkia {
+    fn new<P: AsRef<Path>>(path: P, options: &RenderOptions) -> Result<UsvgWithSkia, Error> {
+        let (tree, warnings) = log_module::capture(|| -> Result<resvg::Tree, Error> {
+            let mut opt = usvg::Options::default();
+            // Get file's absolute directory.
+            opt.resources_dir = std::fs::canonicalize(&path)
+                .ok()
+                .and_then(|p| p.parent().map(|p| p.to_path_buf()));
 
-                renderer.copy(&self.texture, src, dst)?;
+            // generic family, so default font follows configured serif family
+            opt.font_family = String::from("serif");
+            let fontdb = options.fonts.database()?;
 
-                remaining_y -= h;
+            let mut svg_data = std::fs::read(&path)?;
+            if let Some(stylesheet) = &options.stylesheet {
+                svg_data = apply_stylesheet(svg_data, stylesheet);
+                options.warn_unsupported_stylesheet();
             }
-
-            remaining_x -= w;
-        }
-
-        Ok(())
+            let mut tree = usvg::Tree::from_data(&svg_data, &opt).map_err(|e| Error::Parse {
+                backend: "usvg",
+                message: e.to_string(),
+            })?;
+            options.apply_to(&tree.root);
+            tree.convert_text(&fontdb);
+            Ok(resvg::Tree::from_usvg(&tree))
+        });
+        Ok(UsvgWithSkia {
+            tree: tree?,
+            warnings,
+        })
     }
+}
 
-    fn size(&self) -> (u32, u32) {
-        (self.width, self.height)
+#[cfg(feature = "use-usvg")]
+fn load_font_database(fonts: &FontOptions) -> Result<fontdb::Database, Error> {
+    let mut fontdb = fontdb::Database::new();
+    if fonts.system_fonts {
+        fontdb.load_system_fonts();
     }
-
-    fn reposition(&mut self, position: Point) {
-        self.position = position;
+    for dir in &fonts.font_dirs {
+        // fontdb silently skips directories which can't be read
+        fs::read_dir(dir)?;
+        fontdb.load_fonts_dir(dir);
+    }
+    for file in &fonts.font_files {
+        fontdb.load_font_file(file)?;
+    }
+    if let Some(family) = &fonts.serif_family {
+        fontdb.set_serif_family(family);
+    }
+    if let Some(family) = &fonts.sans_serif_family {
+        fontdb.set_sans_serif_family(family);
     }
+    if let Some(family) = &fonts.monospace_family {
+        fontdb.set_monospace_family(family);
+    }
+    debug!("Font database loaded with {} font faces", fontdb.len());
+    Ok(fontdb)
 }
 
-mod digits_display_module {
-    use sdl2::pixels::Color;
-    use sdl2::rect::Point;
-    use sdl2::rect::Rect;
-    use sdl2::render::Texture;
-    use sdl2::render::TextureCreator;
-    use sdl2::render::TextureQuery;
-    use sdl2::ttf::Font;
-    use sdl2::video::WindowContext;
+/// Element of parsed document matched by selectors of user stylesheet
+#[cfg(feature = "use-usvg")]
+struct CssElement<'a, 'input>(roxmltree::Node<'a, 'input>);
 
-    use super::CanvasEntity;
+#[cfg(feature = "use-usvg")]
+impl simplecss::Element for CssElement<'_, '_> {
+    fn parent_element(&self) -> Option<Self> {
+        self.0.parent_element().map(CssElement)
+    }
 
-    // '/' character is not used but it is included to keep ASCII value matching
-    const GLYPHS: &str = "-./0123456789";
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.0.prev_sibling_element().map(CssElement)
+    }
 
-    pub struct DigitsDisplay<'a> {
-        texture: Texture<'a>,
-        glyph_width: u32,
-        glyph_height: u32,
-        position: Point,
-        glyphs: Vec<u8>,
+    fn has_local_name(&self, local_name: &str) -> bool {
+        self.0.tag_name().name() == local_name
     }
 
-    impl<'a> DigitsDisplay<'a> {
-        pub fn new(
-            font: &Font,
-            texture_creator: &'a TextureCreator<WindowContext>,
-        ) -> Result<DigitsDisplay<'a>, String> {
-            let font_surface = font
-                .render(GLYPHS)
-                .blended(Color::RGBA(0, 0, 0, 255))
-                .map_err(|e| e.to_string())?;
+    fn attribute_matches(&self, local_name: &str, operator: simplecss::AttributeOperator) -> bool {
+        match self.0.attribute(local_name) {
+            Some(value) => operator.matches(value),
+            None => false,
+        }
+    }
 
-            let texture = texture_creator
-                .create_texture_from_surface(&font_surface)
-                .map_err(|e| e.to_string())?;
+    fn pseudo_class_matches(&self, class: simplecss::PseudoClass) -> bool {
+        match class {
+            simplecss::PseudoClass::FirstChild => self.prev_sibling_element().is_none(),
+            _ => false,
+        }
+    }
+}
 
-            let TextureQuery { width, height, .. } = texture.query();
-            let glyph_width = width / GLYPHS.len() as u32;
-            let glyph_height = height;
+/// Byte range of attribute starting at `start`, including its quoted value
+#[cfg(feature = "use-usvg")]
+fn attribute_range(text: &str, start: usize) -> Option<std::ops::Range<usize>> {
+    let rest = &text[start..];
+    let value = rest[rest.find('=')? + 1..].trim_start();
+    let quote = value.chars().next()?;
+    let end = rest.len() - value.len() + 1 + value[1..].find(quote)? + 1;
+    Some(start..start + end)
+}
 
-            Ok(DigitsDisplay {
-                texture,
-                glyph_width,
-                glyph_height,
-                position: Point::new(0, 0),
-                glyphs: Vec::with_capacity(64),
-            })
-        }
+#[cfg(feature = "use-usvg")]
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
 
-        pub fn with_i32(&mut self, value: i32) {
-            self.glyphs.clear();
-            for c in format!("{}", value).bytes() {
-                self.glyphs.push(c - '-' as u8);
+/// Applies user stylesheet the way rsvg does: matching declarations replace
+/// presentation attributes, so they are overridden by stylesheets and `style`
+/// attributes of the document regardless of specificity. Important declarations
+/// are appended to `style` attribute. Custom properties and declarations using
+/// `var()` are skipped, as usvg can't resolve them. Documents which can't be parsed are returned unchanged.
+#[cfg(feature = "use-usvg")]
+fn apply_stylesheet(svg_data: Vec<u8>, stylesheet: &str) -> Vec<u8> {
+    // compressed documents have to be decompressed first
+    let svg_data = if svg_data.starts_with(&[0x1f, 0x8b]) {
+        usvg::decompress_svgz(&svg_data).unwrap_or(svg_data)
+    } else {
+        svg_data
+    };
+    let Ok(text) = std::str::from_utf8(&svg_data) else {
+        return svg_data;
+    };
+    let Ok(document) = parse_xml(text) else {
+        return svg_data;
+    };
+    let stylesheet = simplecss::StyleSheet::parse(stylesheet);
+
+    let mut edits = Vec::new();
+    for node in document.descendants().filter(|n| n.is_element()) {
+        // rules are sorted by specificity, later declarations override earlier ones
+        let mut declarations: Vec<&simplecss::Declaration> = Vec::new();
+        let matching = stylesheet
+            .rules
+            .iter()
+            .filter(|rule| rule.selector.matches(&CssElement(node)));
+        for declaration in matching.flat_map(|rule| &rule.declarations) {
+            if declaration.name.starts_with("--") || declaration.value.contains("var(") {
+                continue;
             }
+            declarations.retain(|d| d.name != declaration.name);
+            declarations.push(declaration);
+        }
+        if declarations.is_empty() {
+            continue;
         }
 
-        pub fn with_f64(&mut self, value: f64) {
-            self.glyphs.clear();
-            for c in format!("{:?}", value).bytes() {
-                self.glyphs.push(c - '-' as u8);
+        let start = node.range().start;
+        let tag_end = text[start + 1..]
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .map_or(start + 1, |end| start + 1 + end);
+        let attribute = |name: &str| {
+            node.attributes()
+                .find(|a| a.namespace().is_none() && a.name() == name)
+        };
+        let mut important = Vec::new();
+        for declaration in declarations {
+            if declaration.important {
+                important.push(format!("{}:{}", declaration.name, declaration.value));
+                continue;
+            }
+            let replacement = format!(
+                " {}=\"{}\"",
+                declaration.name,
+                escape_attribute(declaration.value)
+            );
+            match attribute(declaration.name).and_then(|a| attribute_range(text, a.position())) {
+                Some(range) => edits.push((range, replacement[1..].to_string())),
+                None => edits.push((tag_end..tag_end, replacement)),
+            }
+        }
+        if !important.is_empty() {
+            let style = attribute("style")
+                .and_then(|a| Some((a.value(), attribute_range(text, a.position())?)));
+            match style {
+                Some((value, range)) => {
+                    let value = format!("{};{}", value, important.join(";"));
+                    edits.push((range, format!("style=\"{}\"", escape_attribute(&value))));
+                }
+                None => {
+                    let value = escape_attribute(&important.join(";"));
+                    edits.push((tag_end..tag_end, format!(" style=\"{}\"", value)));
+                }
             }
         }
     }
 
-    impl<'a> CanvasEntity for DigitsDisplay<'a> {
-        fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String> {
-            let glyph_width = self.glyph_width;
-            let glyph_height = self.glyph_height;
-
-            let mut m = 0u32;
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut out = String::with_capacity(text.len());
+    let mut position = 0;
+    for (range, replacement) in edits {
+        out.push_str(&text[position..range.start]);
+        out.push_str(&replacement);
+        position = range.end;
+    }
+    out.push_str(&text[position..]);
+    out.into_bytes()
+}
 
-            let mut render = |index: u32, position: u32| -> Result<(), String> {
-                renderer.copy(
-                    &self.texture,
-                    Rect::new((glyph_width * index) as i32, 0, glyph_width, glyph_height),
-                    Rect::new(
-                        self.position.x + (glyph_width * position) as i32,
-                        self.position.y,
-                        glyph_width,
-                        glyph_height,
-                    ),
-                )
-            };
+#[cfg(feature = "use-usvg")]
+impl SvgTextureBuilder for UsvgWithSkia {
+    fn query_size(&self, scale: f64) -> Result<Rect, Error> {
+        let pixmap_size =
+            self.tree
+                .size
+                .to_int_size()
+                .scale_by(scale as f32)
+                .ok_or(Error::Render(format!(
+                    "could not scale SVG by factor of {}",
+                    scale
+                )))?;
+        Ok(Rect::new(0, 0, pixmap_size.width(), pixmap_size.height()))
+    }
 
-            for &digit in self.glyphs.iter() {
-                render(digit as u32, m)?;
-                m += 1;
-            }
-            Ok(())
-        }
+    fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
 
-        fn size(&self) -> (u32, u32) {
-            let TextureQuery { height, .. } = self.texture.query();
-            (self.glyph_width * self.glyphs.len() as u32, height)
-        }
+    fn render_region(&self, scale: f64, region: Rect) -> Result<PixelBuffer, Error> {
+        let mut pixmap = tiny_skia::Pixmap::new(region.width(), region.height())
+            .ok_or(Error::Render(String::from("failed to create new pixmap")))?;
+        let render_ts = tiny_skia::Transform::from_row(
+            scale as f32,
+            0.0,
+            0.0,
+            scale as f32,
+            -region.x() as f32,
+            -region.y() as f32,
+        );
+        self.tree.render(render_ts, &mut pixmap.as_mut());
 
-        fn reposition(&mut self, position: Point) {
-            self.position = position;
-        }
+        Ok(PixelBuffer {
+            width: region.width(),
+            height: region.height(),
+            data: pixmap.take(),
+        })
     }
 }
 
-fn new_static_text<'a>(
-    text: &str,
-    font: &Font,
-    texture_creator: &'a TextureCreator<WindowContext>,
-) -> Result<SimpleCanvasEntity<'a>, String> {
-    let font_surface = font
-        .render(text)
-        .blended(Color::RGBA(0, 0, 0, 255))
-        .map_err(|e| e.to_string())?;
-
-    let texture = texture_creator
-        .create_texture_from_surface(&font_surface)
-        .map_err(|e| e.to_string())?;
-
-    Ok(SimpleCanvasEntity {
-        texture,
-        position: Point::new(0, 0),
-    })
+fn image_error(e: image::ImageError) -> Error {
+    match e {
+        image::ImageError::IoError(e) => Error::Io(e),
+        e => Error::Parse {
+            backend: "image",
+            message: e.to_string(),
+        },
+    }
 }
 
-struct LabeledDigitsDisplay<'a> {
-    label: SimpleCanvasEntity<'a>,
-    digits: digits_display_module::DigitsDisplay<'a>,
+/// Raster image (PNG, JPEG or WebP) resampled to requested scale
+struct RasterImage {
+    image: image::RgbaImage,
 }
 
-impl<'a> LabeledDigitsDisplay<'a> {
-    fn new(
-        text: &str,
-        font: &Font,
-        texture_creator: &'a TextureCreator<WindowContext>,
-    ) -> Result<LabeledDigitsDisplay<'a>, String> {
-        let label = new_static_text(text, &font, &texture_creator)?;
-        let digits = digits_display_module::DigitsDisplay::new(&font, &texture_creator)?;
-        Ok(LabeledDigitsDisplay { label, digits })
+impl RasterImage {
+    const EXTENSIONS: [&'static str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+    fn new<P: AsRef<Path>>(path: P) -> Result<RasterImage, Error> {
+        let image = image::open(path).map_err(image_error)?.to_rgba8();
+        Ok(RasterImage { image })
     }
-}
 
-impl<'a> CanvasEntity for LabeledDigitsDisplay<'a> {
-    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String> {
-        self.label.draw(renderer)?;
-        self.digits.draw(renderer)?;
-        Ok(())
+    fn is_supported(path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| {
+                RasterImage::EXTENSIONS
+                    .iter()
+                    .any(|ext| ext.eq_ignore_ascii_case(e))
+            })
+            .unwrap_or(false)
     }
+}
 
-    fn reposition(&mut self, position: Point) {
-        self.label.reposition(position);
-        self.digits
-            .reposition(position + Point::new(self.label.size().0 as i32, 0));
+impl SvgTextureBuilder for RasterImage {
+    fn query_size(&self, scale: f64) -> Result<Rect, Error> {
+        let width = f64::ceil(f64::from(self.image.width()) * scale) as u32;
+        let height = f64::ceil(f64::from(self.image.height()) * scale) as u32;
+        if width == 0 || height == 0 {
+            return Err(Error::Render(format!(
+                "could not scale image by factor of {}",
+                scale
+            )));
+        }
+        Ok(Rect::new(0, 0, width, height))
     }
 
-    fn size(&self) -> (u32, u32) {
-        (
-            self.label.size().0 + self.digits.size().0,
-            self.label.size().1,
-        )
+    fn render_region(&self, scale: f64, region: Rect) -> Result<PixelBuffer, Error> {
+        let size = self.query_size(scale)?;
+
+        let mut image = if region != size {
+            // only part of the image is visible when zoomed in, pick nearest
+            // source pixels instead of resizing whole image
+            image::RgbaImage::from_fn(region.width(), region.height(), |x, y| {
+                let source_x = (f64::from(region.x() + x as i32) / scale) as u32;
+                let source_y = (f64::from(region.y() + y as i32) / scale) as u32;
+                match self.image.get_pixel_checked(source_x, source_y) {
+                    Some(pixel) => *pixel,
+                    None => image::Rgba([0, 0, 0, 0]),
+                }
+            })
+        } else if size.size() == self.image.dimensions() {
+            self.image.clone()
+        } else {
+            image::imageops::resize(
+                &self.image,
+                size.width(),
+                size.height(),
+                image::imageops::FilterType::Triangle,
+            )
+        };
+
+        // other backends produce premultiplied alpha
+        for pixel in image.pixels_mut() {
+            let a = u16::from(pixel[3]);
+            for c in 0..3 {
+                pixel[c] = (u16::from(pixel[c]) * a / 255) as u8;
+            }
+        }
+
+        Ok(PixelBuffer {
+            width: region.width(),
+            height: region.height(),
+            data: image.into_raw(),
+        })
     }
 }
 
-struct StatusBar<'a> {
-    mouse_x_display: LabeledDigitsDisplay<'a>,
-    mouse_y_display: LabeledDigitsDisplay<'a>,
-    split_display: LabeledDigitsDisplay<'a>,
-    scale_display: LabeledDigitsDisplay<'a>,
+/// Transparent image, used in place of missing file when comparing directories
+/// and in place of files loaded on another thread when only their size is needed
+struct Blank {
+    width: f64,
+    height: f64,
 }
 
-impl<'a> StatusBar<'a> {
-    fn new(
-        font: &Font,
-        texture_creator: &'a TextureCreator<WindowContext>,
-    ) -> Result<StatusBar<'a>, String> {
-        Ok(StatusBar {
-            mouse_x_display: LabeledDigitsDisplay::new("x:", &font, &texture_creator)?,
-            mouse_y_display: LabeledDigitsDisplay::new(" y:", &font, &texture_creator)?,
-            split_display: LabeledDigitsDisplay::new(" split:", &font, &texture_creator)?,
-            scale_display: LabeledDigitsDisplay::new(" scale:", &font, &texture_creator)?,
+impl Blank {
+    fn with_size_of(other: &dyn SvgTextureBuilder) -> Result<Blank, Error> {
+        let size = other.query_size(1.0)?;
+        Ok(Blank {
+            width: f64::from(size.width()),
+            height: f64::from(size.height()),
         })
     }
+}
 
-    fn update(&mut self, x: i32, y: i32, split: i32, scale: f64) {
-        self.mouse_x_display.digits.with_i32(x);
-        self.mouse_y_display.digits.with_i32(y);
-        self.split_display.digits.with_i32(split);
-        self.scale_display.digits.with_f64(scale);
+impl SvgTextureBuilder for Blank {
+    fn query_size(&self, scale: f64) -> Result<Rect, Error> {
+        let width = f64::ceil(self.width * scale) as u32;
+        let height = f64::ceil(self.height * scale) as u32;
+        Ok(Rect::new(0, 0, width, height))
+    }
+
+    fn render_region(&self, _scale: f64, region: Rect) -> Result<PixelBuffer, Error> {
+        Ok(PixelBuffer {
+            width: region.width(),
+            height: region.height(),
+            data: vec![0; (region.width() * region.height()) as usize * 4],
+        })
     }
 }
 
-impl<'a> CanvasEntity for StatusBar<'a> {
-    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String> {
-        self.mouse_x_display.draw(renderer)?;
-        self.mouse_y_display.draw(renderer)?;
-        self.split_display.draw(renderer)?;
-        self.scale_display.draw(renderer)?;
+trait CanvasEntity {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error>;
+
+    fn size(&self) -> (u32, u32);
+
+    fn reposition(&mut self, position: Point);
+
+    fn center_on(&mut self, position: Point) {
+        let size = self.size();
+        let offset = Point::new(size.0 as i32 / 2, size.1 as i32 / 2);
+        self.reposition(position - offset)
+    }
+}
+
+/// Entity of single fixed texture which is always drawn fully
+/// (without clipping, stretching etc) to given position
+struct SimpleCanvasEntity<'a> {
+    texture: Texture<'a>,
+    position: Point,
+}
+
+impl<'a> CanvasEntity for SimpleCanvasEntity<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        renderer
+            .copy(
+                &self.texture,
+                None,
+                Rect::new(
+                    self.position.x,
+                    self.position.y,
+                    self.size().0,
+                    self.size().1,
+                ),
+            )
+            .map_err(Error::Display)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let TextureQuery { width, height, .. } = self.texture.query();
+        (width, height)
+    }
+
+    fn reposition(&mut self, position: Point) {
+        self.position = position;
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// Direction of the line separating left and right images
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Orientation {
+    /// Left image on the left, right image on the right, split along x axis
+    Vertical,
+    /// Left image on the top, right image on the bottom, split along y axis
+    Horizontal,
+}
+
+struct SplitView<'a> {
+    texture: Texture<'a>,
+    width: u32,
+    height: u32,
+    /// Part of the image held by the texture, whole image unless it exceeds
+    /// maximum texture size
+    region: Rect,
+    side: Side,
+    orientation: Orientation,
+    position: Point,
+    split: u32,
+}
+
+impl<'a> SplitView<'a> {
+    fn new(texture: Texture<'a>, side: Side, size: (u32, u32), region: Rect) -> SplitView<'a> {
+        SplitView {
+            texture,
+            width: size.0,
+            height: size.1,
+            region,
+            side,
+            orientation: Orientation::Vertical,
+            position: Point::new(0, 0),
+            split: 0,
+        }
+    }
+
+    fn split(&mut self, split: u32) {
+        let max = match self.orientation {
+            Orientation::Vertical => self.width,
+            Orientation::Horizontal => self.height,
+        };
+        self.split = split.clamp(0, max)
+    }
+
+    /// Scales the view without rendering texture again
+    fn rescale(&mut self, factor: f64) {
+        let (size, region) = rescale_view((self.width, self.height), self.region, factor);
+        (self.width, self.height) = size;
+        self.region = region;
+    }
+
+    /// Draws the whole image, ignoring split, with top left corner at `position`
+    fn draw_whole(
+        &self,
+        renderer: &mut sdl2::render::WindowCanvas,
+        position: Point,
+    ) -> Result<(), Error> {
+        let dst = Rect::new(
+            position.x + self.region.x(),
+            position.y + self.region.y(),
+            self.region.width(),
+            self.region.height(),
+        );
+        renderer
+            .copy(&self.texture, None, dst)
+            .map_err(Error::Display)
+    }
+}
+
+impl<'a> CanvasEntity for SplitView<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        // part of the image in image coordinates
+        let src = match (self.orientation, self.side) {
+            (Orientation::Vertical, Side::Left) => Rect::new(0, 0, self.split, self.height),
+            (Orientation::Vertical, Side::Right) => {
+                let left_x =
+                    i32::try_from(self.split).map_err(|e| Error::Display(e.to_string()))?;
+                Rect::new(left_x, 0, self.width - self.split, self.height)
+            }
+            (Orientation::Horizontal, Side::Left) => Rect::new(0, 0, self.width, self.split),
+            (Orientation::Horizontal, Side::Right) => {
+                let top_y = i32::try_from(self.split).map_err(|e| Error::Display(e.to_string()))?;
+                Rect::new(0, top_y, self.width, self.height - self.split)
+            }
+        };
+
+        // Rect type has to have width greater than 0 so we should better detect
+        // case when split is out of texture and do not render corresponding part.
+        // Right now we will render 1px width slice in such scenario.
+        let src = match src.intersection(self.region) {
+            Some(src) => src,
+            None => return Ok(()),
+        };
+        let dst = Rect::new(
+            self.position.x + src.x(),
+            self.position.y + src.y(),
+            src.width(),
+            src.height(),
+        );
+        // texture is drawn scaled while rendering at new scale is in progress
+        let query = self.texture.query();
+        let scale_x = f64::from(query.width) / f64::from(self.region.width());
+        let scale_y = f64::from(query.height) / f64::from(self.region.height());
+        let src = Rect::new(
+            (f64::from(src.x() - self.region.x()) * scale_x) as i32,
+            (f64::from(src.y() - self.region.y()) * scale_y) as i32,
+            (f64::from(src.width()) * scale_x).round() as u32,
+            (f64::from(src.height()) * scale_y).round() as u32,
+        );
+        renderer.copy(&self.texture, src, dst)?;
+
         Ok(())
     }
 
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
     fn reposition(&mut self, position: Point) {
-        fn reposition_internal<T: CanvasEntity>(element: &mut T, position: Point) -> Point {
-            element.reposition(position);
-            position + Point::new(element.size().0 as i32, 0)
+        self.position = position;
+    }
+}
+
+/// Size of the image and region held by its texture scaled by `factor`
+fn rescale_view(size: (u32, u32), region: Rect, factor: f64) -> ((u32, u32), Rect) {
+    let scale = |value: u32| cmp::max((f64::from(value) * factor).round() as u32, 1);
+    let region = Rect::new(
+        (f64::from(region.x()) * factor).round() as i32,
+        (f64::from(region.y()) * factor).round() as i32,
+        scale(region.width()),
+        scale(region.height()),
+    );
+    ((scale(size.0), scale(size.1)), region)
+}
+
+/// Absolute difference of both images, positioned the same way as [`Diff`]
+struct DifferenceView<'a> {
+    texture: Texture<'a>,
+    width: u32,
+    height: u32,
+    /// Part of the image held by the texture
+    region: Rect,
+    position: Point,
+}
+
+impl<'a> DifferenceView<'a> {
+    fn new(texture: Texture<'a>, size: (u32, u32), region: Rect) -> DifferenceView<'a> {
+        DifferenceView {
+            texture,
+            width: size.0,
+            height: size.1,
+            region,
+            position: Point::new(0, 0),
         }
+    }
 
-        let mut p = position;
-        p = reposition_internal(&mut self.mouse_x_display, p);
-        p = reposition_internal(&mut self.mouse_y_display, p);
-        p = reposition_internal(&mut self.split_display, p);
-        _ = reposition_internal(&mut self.scale_display, p);
+    /// Scales the view without rendering texture again
+    fn rescale(&mut self, factor: f64) {
+        let (size, region) = rescale_view((self.width, self.height), self.region, factor);
+        (self.width, self.height) = size;
+        self.region = region;
+    }
+}
+
+impl<'a> CanvasEntity for DifferenceView<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        let dst = Rect::new(
+            self.position.x + self.region.x(),
+            self.position.y + self.region.y(),
+            self.region.width(),
+            self.region.height(),
+        );
+        renderer
+            .copy(&self.texture, None, dst)
+            .map_err(Error::Display)
     }
 
     fn size(&self) -> (u32, u32) {
-        (
-            self.mouse_x_display.size().0
-                + self.mouse_y_display.size().0
-                + self.split_display.size().0
-                + self.scale_display.size().0,
-            self.mouse_x_display.size().1,
-        )
+        (self.width, self.height)
+    }
+
+    fn reposition(&mut self, position: Point) {
+        self.position = position;
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum DiffMode {
+    /// Left and right images separated by movable split
+    Swipe,
+    /// Right image drawn over the left image with adjustable opacity
+    OnionSkin,
+    /// Left and right images drawn next to each other, each in its own half
+    /// of the viewport
+    SideBySide,
+    /// Full left or full right image, alternated on timer
+    Blink,
+}
+
+struct Diff<'a> {
+    left: SplitView<'a>,
+    right: SplitView<'a>,
+    position: Point,
+    split: u32,
+    orientation: Orientation,
+    mode: DiffMode,
+    /// Opacity of the right image in onion skin mode, in percents
+    opacity: u8,
+    /// Last known mouse position, used for drawing crosshair in side by side mode
+    cursor: Point,
+    /// Image shown in blink mode
+    blink_side: Side,
+}
+
+impl<'a> Diff<'a> {
+    const OPACITY_STEP: u8 = 10;
+
+    fn new(
+        left: Texture<'a>,
+        right: Texture<'a>,
+        left_size: (u32, u32),
+        right_size: (u32, u32),
+        region: Rect,
+    ) -> Diff<'a> {
+        let mut left = SplitView::new(left, Side::Left, left_size, region);
+        let mut right = SplitView::new(right, Side::Right, right_size, region);
+        let position = Point::new(0, 0);
+        let split = cmp::min(left.width, right.width) / 2;
+        left.split(split);
+        right.split(split);
+        Diff {
+            left,
+            right,
+            position,
+            split,
+            orientation: Orientation::Vertical,
+            mode: DiffMode::Swipe,
+            opacity: 50,
+            cursor: Point::new(0, 0),
+            blink_side: Side::Left,
+        }
+    }
+
+    /// Applies view settings (split, orientation, mode etc.) of other diff, used
+    /// when diff is recreated with new textures
+    fn copy_settings(&mut self, other: &Diff) {
+        self.set_orientation(other.orientation);
+        self.split_by_fraction(other.get_left_fraction());
+        self.set_opacity(other.opacity);
+        self.set_mode(other.mode);
+        self.blink_side = other.blink_side;
+    }
+
+    fn set_mode(&mut self, mode: DiffMode) {
+        self.mode = mode;
+        self.update_alpha();
+        debug!("New diff mode {:?}", self.mode);
+    }
+
+    /// Switches to given mode or back to swipe mode if given mode is already active
+    fn toggle_mode(&mut self, mode: DiffMode) {
+        if self.mode == mode {
+            self.set_mode(DiffMode::Swipe);
+        } else {
+            self.set_mode(mode);
+        }
+    }
+
+    fn set_opacity(&mut self, opacity: u8) {
+        self.opacity = opacity.clamp(0, 100);
+        self.update_alpha();
+        debug!("New opacity {:?}", self.opacity);
+    }
+
+    fn increase_opacity(&mut self) {
+        self.set_opacity(self.opacity.saturating_add(Diff::OPACITY_STEP));
+    }
+
+    fn decrease_opacity(&mut self) {
+        self.set_opacity(self.opacity.saturating_sub(Diff::OPACITY_STEP));
+    }
+
+    fn update_alpha(&mut self) {
+        let alpha = match self.mode {
+            DiffMode::Swipe | DiffMode::SideBySide | DiffMode::Blink => 255,
+            DiffMode::OnionSkin => (u32::from(self.opacity) * 255 / 100) as u8,
+        };
+        self.right.texture.set_alpha_mod(alpha);
+    }
+
+    fn update_split(&mut self, split: u32) {
+        self.split = split;
+        self.left.split(self.split);
+        self.right.split(self.split);
+        debug!("New split position {:?}", self.split);
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        let fraction = self.get_left_fraction();
+        self.orientation = orientation;
+        self.left.orientation = orientation;
+        self.right.orientation = orientation;
+        self.split_by_fraction(fraction);
+        debug!("New split orientation {:?}", self.orientation);
+    }
+
+    fn toggle_orientation(&mut self) {
+        let orientation = match self.orientation {
+            Orientation::Vertical => Orientation::Horizontal,
+            Orientation::Horizontal => Orientation::Vertical,
+        };
+        self.set_orientation(orientation);
+    }
+
+    /// Size of the diff along the axis on which split is moved
+    fn split_range(&self) -> u32 {
+        match self.orientation {
+            Orientation::Vertical => self.size().0,
+            Orientation::Horizontal => self.size().1,
+        }
+    }
+
+    /// Clip rectangles and image positions of left and right halves of the viewport
+    /// in side by side mode. Each image is moved from the viewport center to the
+    /// center of its half, so both keep the same pan offset.
+    fn side_by_side_layout(&self, viewport: Rect) -> [(Rect, Point); 2] {
+        let half_width = viewport.width() / 2;
+        let offset = Point::new(half_width as i32 / 2, 0);
+        [
+            (
+                Rect::new(viewport.x(), viewport.y(), half_width, viewport.height()),
+                self.position - offset,
+            ),
+            (
+                Rect::new(
+                    viewport.x() + half_width as i32,
+                    viewport.y(),
+                    viewport.width() - half_width,
+                    viewport.height(),
+                ),
+                self.position + offset,
+            ),
+        ]
+    }
+
+    /// Position of the cursor in image coordinates
+    fn cursor_position(&self, viewport: Rect) -> Point {
+        self.image_position(self.cursor, viewport)
+    }
+
+    /// Converts window coordinates to image coordinates
+    fn image_position(&self, point: Point, viewport: Rect) -> Point {
+        if self.mode == DiffMode::SideBySide {
+            for (clip, position) in self.side_by_side_layout(viewport) {
+                if clip.contains_point(point) {
+                    return point - position;
+                }
+            }
+        }
+        point - self.position
+    }
+
+    fn update(&mut self, state: &MouseState) {
+        self.cursor = Point::new(state.x(), state.y());
+        if self.mode == DiffMode::Swipe && state.is_mouse_button_pressed(MouseButton::Left) {
+            let offset = match self.orientation {
+                Orientation::Vertical => state.x() - self.position.x(),
+                Orientation::Horizontal => state.y() - self.position.y(),
+            };
+            let split = u32::try_from(offset)
+                .unwrap_or(0)
+                .clamp(0, self.split_range());
+            self.update_split(split);
+        }
+    }
+
+    /// Part of the image rendered into textures
+    fn region(&self) -> Rect {
+        self.left.region
+    }
+
+    /// Shows current textures scaled by `factor` until new ones are rendered
+    fn rescale(&mut self, factor: f64) {
+        let fraction = self.get_left_fraction();
+        self.left.rescale(factor);
+        self.right.rescale(factor);
+        self.split_by_fraction(fraction);
+    }
+
+    fn split_by_fraction(&mut self, fraction: f64) {
+        let split = (fraction.clamp(0.0, 1.0) * f64::from(self.split_range())) as u32;
+        self.update_split(split);
+    }
+
+    fn get_left_fraction(&self) -> f64 {
+        f64::from(self.split) / f64::from(self.split_range())
+    }
+}
+
+impl<'a> CanvasEntity for Diff<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        if self.mode == DiffMode::OnionSkin {
+            for view in [&self.left, &self.right] {
+                view.draw_whole(renderer, self.position)?;
+            }
+            return Ok(());
+        }
+
+        if self.mode == DiffMode::Blink {
+            let view = match self.blink_side {
+                Side::Left => &self.left,
+                Side::Right => &self.right,
+            };
+            view.draw_whole(renderer, self.position)?;
+            return Ok(());
+        }
+
+        if self.mode == DiffMode::SideBySide {
+            let viewport = renderer.viewport();
+            let cursor = self.cursor_position(viewport);
+            let layout = self.side_by_side_layout(viewport);
+
+            for ((clip, position), view) in layout.iter().zip([&self.left, &self.right]) {
+                renderer.set_clip_rect(*clip);
+                view.draw_whole(renderer, *position)?;
+
+                // crosshair at the same image coordinate in both halves
+                let crosshair = *position + cursor;
+                renderer.set_draw_color(Color::RGB(0, 0, 255));
+                renderer.draw_line(
+                    Point::new(clip.left(), crosshair.y),
+                    Point::new(clip.right(), crosshair.y),
+                )?;
+                renderer.draw_line(
+                    Point::new(crosshair.x, clip.top()),
+                    Point::new(crosshair.x, clip.bottom()),
+                )?;
+            }
+            renderer.set_clip_rect(None);
+
+            // draw halves separator
+            let separator_x = layout[1].0.x();
+            renderer.set_draw_color(Color::RGB(255, 0, 0));
+            renderer.fill_rect(Rect::new(
+                separator_x - 1,
+                viewport.y(),
+                3,
+                viewport.height(),
+            ))?;
+
+            return Ok(());
+        }
+
+        self.left.draw(renderer)?;
+        self.right.draw(renderer)?;
+
+        // draw left/right separator
+        let split = i32::try_from(self.split).map_err(|e| Error::Display(e.to_string()))?;
+        let (width, height) = self.size();
+        let separator = match self.orientation {
+            Orientation::Vertical => Rect::new(self.position.x + split, self.position.y, 3, height),
+            Orientation::Horizontal => {
+                Rect::new(self.position.x, self.position.y + split, width, 3)
+            }
+        };
+        renderer.set_draw_color(Color::RGB(255, 0, 0));
+        renderer.fill_rect(separator)?;
+
+        Ok(())
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let width = cmp::max(self.left.width, self.right.width);
+        let height = cmp::max(self.left.height, self.right.height);
+        (width, height)
+    }
+
+    fn reposition(&mut self, position: Point) {
+        self.left.reposition(position);
+        self.right.reposition(position);
+        self.position = position;
+    }
+}
+
+struct CheckerBoard<'a> {
+    texture: Texture<'a>,
+    width: u32,
+    height: u32,
+    position: Point,
+}
+
+impl<'a> CheckerBoard<'a> {
+    const SQUARE_SIZE: u32 = 8;
+    const WIDTH: u32 = CheckerBoard::SQUARE_SIZE * 64;
+    const HEIGHT: u32 = CheckerBoard::WIDTH;
+
+    fn new(
+        texture_creator: &'a TextureCreator<WindowContext>,
+        size: (u32, u32),
+    ) -> Result<CheckerBoard<'a>, Error> {
+        // Create pixel data for the checkerboard pattern
+        let mut pixels =
+            Vec::with_capacity((CheckerBoard::WIDTH * CheckerBoard::HEIGHT) as usize * 4);
+
+        for y in 0..CheckerBoard::HEIGHT {
+            for x in 0..CheckerBoard::WIDTH {
+                let color =
+                    if (x / CheckerBoard::SQUARE_SIZE + y / CheckerBoard::SQUARE_SIZE) % 2 == 0 {
+                        Color::RGB(189, 189, 189)
+                    } else {
+                        Color::RGB(209, 209, 209)
+                    };
+
+                let (r, g, b, a) = color.rgba();
+                pixels.push(r);
+                pixels.push(g);
+                pixels.push(b);
+                pixels.push(a);
+            }
+        }
+
+        // Create a Surface and load pixel data
+        let surface = sdl2::surface::Surface::from_data(
+            &mut pixels,
+            CheckerBoard::WIDTH,
+            CheckerBoard::HEIGHT,
+            4 * CheckerBoard::WIDTH,
+            sdl2::pixels::PixelFormatEnum::ARGB8888,
+        )?;
+
+        // Convert the Surface to a Texture
+        let texture = texture_creator
+            .create_texture_from_surface(surface)
+            .map_err(|e| Error::Display(e.to_string()))?;
+        let position = Point::new(0, 0);
+
+        Ok(CheckerBoard {
+            texture,
+            width: size.0,
+            height: size.1,
+            position,
+        })
+    }
+
+    fn set_size(&mut self, size: (u32, u32)) -> &mut CheckerBoard<'a> {
+        self.width = size.0;
+        self.height = size.1;
+        self
+    }
+}
+
+impl<'a> CanvasEntity for CheckerBoard<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        // draw only tiles intersecting viewport, zoomed in area can be huge
+        let area = Rect::new(self.position.x, self.position.y, self.width, self.height);
+        let visible = match area.intersection(renderer.viewport()) {
+            Some(visible) => visible,
+            None => return Ok(()),
+        };
+        let tile_width = CheckerBoard::WIDTH as i32;
+        let tile_height = CheckerBoard::HEIGHT as i32;
+        let first_x = self.position.x + (visible.x() - self.position.x) / tile_width * tile_width;
+        let first_y = self.position.y + (visible.y() - self.position.y) / tile_height * tile_height;
+
+        let mut y = first_y;
+        while y < visible.bottom() {
+            let mut x = first_x;
+            while x < visible.right() {
+                let tile = Rect::new(x, y, CheckerBoard::WIDTH, CheckerBoard::HEIGHT);
+                if let Some(dst) = tile.intersection(area) {
+                    let src = Rect::new(dst.x() - x, dst.y() - y, dst.width(), dst.height());
+                    renderer.copy(&self.texture, src, dst)?;
+                }
+                x += tile_width;
+            }
+            y += tile_height;
+        }
+
+        Ok(())
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn reposition(&mut self, position: Point) {
+        self.position = position;
+    }
+}
+
+mod digits_display_module {
+    use super::Error;
+    use sdl2::pixels::Color;
+    use sdl2::rect::Point;
+    use sdl2::rect::Rect;
+    use sdl2::render::Texture;
+    use sdl2::render::TextureCreator;
+    use sdl2::render::TextureQuery;
+    use sdl2::ttf::Font;
+    use sdl2::video::WindowContext;
+
+    use super::CanvasEntity;
+
+    // '/' character is not used but it is included to keep ASCII value matching
+    const GLYPHS: &str = "-./0123456789";
+
+    pub struct DigitsDisplay<'a> {
+        texture: Texture<'a>,
+        glyph_width: u32,
+        glyph_height: u32,
+        position: Point,
+        glyphs: Vec<u8>,
+    }
+
+    impl<'a> DigitsDisplay<'a> {
+        pub fn new(
+            font: &Font,
+            texture_creator: &'a TextureCreator<WindowContext>,
+        ) -> Result<DigitsDisplay<'a>, Error> {
+            let font_surface = font
+                .render(GLYPHS)
+                .blended(Color::RGBA(0, 0, 0, 255))
+                .map_err(|e| Error::Display(e.to_string()))?;
+
+            let texture = texture_creator
+                .create_texture_from_surface(&font_surface)
+                .map_err(|e| Error::Display(e.to_string()))?;
+
+            let TextureQuery { width, height, .. } = texture.query();
+            let glyph_width = width / GLYPHS.len() as u32;
+            let glyph_height = height;
+
+            Ok(DigitsDisplay {
+                texture,
+                glyph_width,
+                glyph_height,
+                position: Point::new(0, 0),
+                glyphs: Vec::with_capacity(64),
+            })
+        }
+
+        pub fn with_i32(&mut self, value: i32) {
+            self.glyphs.clear();
+            for c in format!("{}", value).bytes() {
+                self.glyphs.push(c - '-' as u8);
+            }
+        }
+
+        pub fn with_f64(&mut self, value: f64) {
+            self.glyphs.clear();
+            // limit precision but keep at least one decimal digit
+            let text = format!("{:.4}", value);
+            let text = text.trim_end_matches('0');
+            let text = if text.ends_with('.') {
+                format!("{}0", text)
+            } else {
+                text.to_string()
+            };
+            for c in text.bytes() {
+                self.glyphs.push(c - '-' as u8);
+            }
+        }
+    }
+
+    impl<'a> CanvasEntity for DigitsDisplay<'a> {
+        fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+            let glyph_width = self.glyph_width;
+            let glyph_height = self.glyph_height;
+
+            let mut m = 0u32;
+
+            let mut render = |index: u32, position: u32| -> Result<(), Error> {
+                renderer
+                    .copy(
+                        &self.texture,
+                        Rect::new((glyph_width * index) as i32, 0, glyph_width, glyph_height),
+                        Rect::new(
+                            self.position.x + (glyph_width * position) as i32,
+                            self.position.y,
+                            glyph_width,
+                            glyph_height,
+                        ),
+                    )
+                    .map_err(Error::Display)
+            };
+
+            for &digit in self.glyphs.iter() {
+                render(digit as u32, m)?;
+                m += 1;
+            }
+            Ok(())
+        }
+
+        fn size(&self) -> (u32, u32) {
+            let TextureQuery { height, .. } = self.texture.query();
+            (self.glyph_width * self.glyphs.len() as u32, height)
+        }
+
+        fn reposition(&mut self, position: Point) {
+            self.position = position;
+        }
+    }
+}
+
+fn new_static_text<'a>(
+    text: &str,
+    font: &Font,
+    texture_creator: &'a TextureCreator<WindowContext>,
+) -> Result<SimpleCanvasEntity<'a>, Error> {
+    let font_surface = font
+        .render(text)
+        .blended(Color::RGBA(0, 0, 0, 255))
+        .map_err(|e| Error::Display(e.to_string()))?;
+
+    let texture = texture_creator
+        .create_texture_from_surface(&font_surface)
+        .map_err(|e| Error::Display(e.to_string()))?;
+
+    Ok(SimpleCanvasEntity {
+        texture,
+        position: Point::new(0, 0),
+    })
+}
+
+struct LabeledDigitsDisplay<'a> {
+    label: SimpleCanvasEntity<'a>,
+    digits: digits_display_module::DigitsDisplay<'a>,
+}
+
+impl<'a> LabeledDigitsDisplay<'a> {
+    fn new(
+        text: &str,
+        font: &Font,
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<LabeledDigitsDisplay<'a>, Error> {
+        let label = new_static_text(text, &font, &texture_creator)?;
+        let digits = digits_display_module::DigitsDisplay::new(&font, &texture_creator)?;
+        Ok(LabeledDigitsDisplay { label, digits })
+    }
+}
+
+impl<'a> CanvasEntity for LabeledDigitsDisplay<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        self.label.draw(renderer)?;
+        self.digits.draw(renderer)?;
+        Ok(())
+    }
+
+    fn reposition(&mut self, position: Point) {
+        self.label.reposition(position);
+        self.digits
+            .reposition(position + Point::new(self.label.size().0 as i32, 0));
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (
+            self.label.size().0 + self.digits.size().0,
+            self.label.size().1,
+        )
+    }
+}
+
+struct StatusBar<'a> {
+    mouse_x_display: LabeledDigitsDisplay<'a>,
+    mouse_y_display: LabeledDigitsDisplay<'a>,
+    split_display: LabeledDigitsDisplay<'a>,
+    scale_display: LabeledDigitsDisplay<'a>,
+    opacity_display: LabeledDigitsDisplay<'a>,
+    show_opacity: bool,
+    /// Similarity of images at current scale
+    metrics_display: Option<SimpleCanvasEntity<'a>>,
+    /// Number of backend warnings of each side, hidden when there are none
+    warnings_display: Option<SimpleCanvasEntity<'a>>,
+    blink_left_display: SimpleCanvasEntity<'a>,
+    blink_right_display: SimpleCanvasEntity<'a>,
+    blink_paused_display: SimpleCanvasEntity<'a>,
+    /// Side shown in blink mode and its pause state
+    blink: Option<(Side, bool)>,
+    rendering_display: SimpleCanvasEntity<'a>,
+    rendering: bool,
+}
+
+impl<'a> StatusBar<'a> {
+    fn new(
+        font: &Font,
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<StatusBar<'a>, Error> {
+        Ok(StatusBar {
+            mouse_x_display: LabeledDigitsDisplay::new("x:", &font, &texture_creator)?,
+            mouse_y_display: LabeledDigitsDisplay::new(" y:", &font, &texture_creator)?,
+            split_display: LabeledDigitsDisplay::new(" split:", &font, &texture_creator)?,
+            scale_display: LabeledDigitsDisplay::new(" scale:", &font, &texture_creator)?,
+            opacity_display: LabeledDigitsDisplay::new(" opacity:", font, texture_creator)?,
+            show_opacity: false,
+            metrics_display: None,
+            warnings_display: None,
+            blink_left_display: new_static_text(" showing: left", font, texture_creator)?,
+            blink_right_display: new_static_text(" showing: right", font, texture_creator)?,
+            blink_paused_display: new_static_text(" (paused)", font, texture_creator)?,
+            blink: None,
+            rendering_display: new_static_text(" rendering…", font, texture_creator)?,
+            rendering: false,
+        })
+    }
+
+    /// Shows comparison metrics, `whole` is `false` when only the rendered region
+    /// around the visible part of images was compared
+    fn set_metrics(
+        &mut self,
+        comparison: &Comparison,
+        whole: bool,
+        font: &Font,
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<(), Error> {
+        let text = if whole {
+            format!(" {}", comparison)
+        } else {
+            format!(" region {}", comparison)
+        };
+        self.metrics_display = Some(new_static_text(&text, font, texture_creator)?);
+        Ok(())
+    }
+
+    fn set_warnings(
+        &mut self,
+        warnings: &WarningPair,
+        font: &Font,
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<(), Error> {
+        self.warnings_display = match (warnings.0.len(), warnings.1.len()) {
+            (0, 0) => None,
+            (left, right) => {
+                let text = format!(" warnings: {}|{} (W)", left, right);
+                Some(new_static_text(&text, font, texture_creator)?)
+            }
+        };
+        Ok(())
+    }
+
+    /// Shows indicator of background rendering in progress
+    fn set_rendering(&mut self, rendering: bool) {
+        self.rendering = rendering;
+    }
+
+    fn update(
+        &mut self,
+        x: i32,
+        y: i32,
+        split: i32,
+        scale: f64,
+        opacity: Option<u8>,
+        blink: Option<(Side, bool)>,
+    ) {
+        self.mouse_x_display.digits.with_i32(x);
+        self.mouse_y_display.digits.with_i32(y);
+        self.split_display.digits.with_i32(split);
+        self.scale_display.digits.with_f64(scale);
+        self.show_opacity = opacity.is_some();
+        if let Some(opacity) = opacity {
+            self.opacity_display.digits.with_i32(i32::from(opacity));
+        }
+        self.blink = blink;
+    }
+
+    fn blink_side_display(&self, side: Side) -> &SimpleCanvasEntity<'a> {
+        match side {
+            Side::Left => &self.blink_left_display,
+            Side::Right => &self.blink_right_display,
+        }
+    }
+}
+
+impl<'a> CanvasEntity for StatusBar<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        self.mouse_x_display.draw(renderer)?;
+        self.mouse_y_display.draw(renderer)?;
+        self.split_display.draw(renderer)?;
+        self.scale_display.draw(renderer)?;
+        if self.show_opacity {
+            self.opacity_display.draw(renderer)?;
+        }
+        if let Some(metrics_display) = &self.metrics_display {
+            metrics_display.draw(renderer)?;
+        }
+        if let Some(warnings_display) = &self.warnings_display {
+            warnings_display.draw(renderer)?;
+        }
+        if let Some((side, paused)) = self.blink {
+            self.blink_side_display(side).draw(renderer)?;
+            if paused {
+                self.blink_paused_display.draw(renderer)?;
+            }
+        }
+        if self.rendering {
+            self.rendering_display.draw(renderer)?;
+        }
+        Ok(())
+    }
+
+    fn reposition(&mut self, position: Point) {
+        fn reposition_internal<T: CanvasEntity>(element: &mut T, position: Point) -> Point {
+            element.reposition(position);
+            position + Point::new(element.size().0 as i32, 0)
+        }
+
+        let mut p = position;
+        p = reposition_internal(&mut self.mouse_x_display, p);
+        p = reposition_internal(&mut self.mouse_y_display, p);
+        p = reposition_internal(&mut self.split_display, p);
+        p = reposition_internal(&mut self.scale_display, p);
+        if self.show_opacity {
+            p = reposition_internal(&mut self.opacity_display, p);
+        }
+        if let Some(metrics_display) = &mut self.metrics_display {
+            p = reposition_internal(metrics_display, p);
+        }
+        if let Some(warnings_display) = &mut self.warnings_display {
+            p = reposition_internal(warnings_display, p);
+        }
+        let position_before_blink = p;
+        // only one of the side labels is visible at a time
+        let left_end = reposition_internal(&mut self.blink_left_display, p);
+        let right_end = reposition_internal(&mut self.blink_right_display, p);
+        p = match self.blink {
+            Some((Side::Right, _)) => right_end,
+            _ => left_end,
+        };
+        let paused_end = reposition_internal(&mut self.blink_paused_display, p);
+        p = match self.blink {
+            Some((_, true)) => paused_end,
+            Some(_) => p,
+            None => position_before_blink,
+        };
+        _ = reposition_internal(&mut self.rendering_display, p);
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let opacity_width = if self.show_opacity {
+            self.opacity_display.size().0
+        } else {
+            0
+        };
+        let blink_width = match self.blink {
+            Some((side, paused)) => {
+                self.blink_side_display(side).size().0
+                    + if paused {
+                        self.blink_paused_display.size().0
+                    } else {
+                        0
+                    }
+            }
+            None => 0,
+        };
+        let metrics_width = self
+            .metrics_display
+            .as_ref()
+            .map(|m| m.size().0)
+            .unwrap_or(0);
+        let warnings_width = self
+            .warnings_display
+            .as_ref()
+            .map(|w| w.size().0)
+            .unwrap_or(0);
+        let rendering_width = if self.rendering {
+            self.rendering_display.size().0
+        } else {
+            0
+        };
+        (
+            self.mouse_x_display.size().0
+                + self.mouse_y_display.size().0
+                + self.split_display.size().0
+                + self.scale_display.size().0
+                + opacity_width
+                + metrics_width
+                + warnings_width
+                + blink_width
+                + rendering_width,
+            self.mouse_x_display.size().1,
+        )
+    }
+}
+
+struct MessageBar<'a> {
+    message: SimpleCanvasEntity<'a>,
+}
+
+impl<'a> MessageBar<'a> {
+    fn new(
+        text: &str,
+        font: &Font,
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<MessageBar<'a>, Error> {
+        Ok(MessageBar {
+            message: new_static_text(text, font, texture_creator)?,
+        })
+    }
+}
+
+impl<'a> CanvasEntity for MessageBar<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        self.message.draw(renderer)?;
+        Ok(())
+    }
+
+    fn reposition(&mut self, position: Point) {
+        self.message.reposition(position)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.message.size()
+    }
+}
+
+/// List of backend warnings drawn over images, one line per warning
+struct WarningsPanel<'a> {
+    lines: Vec<SimpleCanvasEntity<'a>>,
+    position: Point,
+}
+
+impl<'a> WarningsPanel<'a> {
+    fn new(
+        warnings: &WarningPair,
+        font: &Font,
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Result<WarningsPanel<'a>, Error> {
+        let left = warnings.0.iter().map(|w| format!("left: {}", w));
+        let right = warnings.1.iter().map(|w| format!("right: {}", w));
+        let mut text: Vec<String> = left.chain(right).collect();
+        if text.is_empty() {
+            text.push(String::from("no warnings"));
+        }
+        let lines = text
+            .iter()
+            .map(|line| new_static_text(line, font, texture_creator))
+            .collect::<Result<_, _>>()?;
+        Ok(WarningsPanel {
+            lines,
+            position: Point::new(0, 0),
+        })
+    }
+}
+
+impl<'a> CanvasEntity for WarningsPanel<'a> {
+    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), Error> {
+        let (width, height) = self.size();
+        renderer.set_blend_mode(sdl2::render::BlendMode::Blend);
+        renderer.set_draw_color(Color::RGBA(255, 255, 255, 224));
+        renderer.fill_rect(Rect::new(self.position.x, self.position.y, width, height))?;
+        for line in &self.lines {
+            line.draw(renderer)?;
+        }
+        Ok(())
+    }
+
+    fn reposition(&mut self, position: Point) {
+        self.position = position;
+        let mut p = position;
+        for line in &mut self.lines {
+            line.reposition(p);
+            p += Point::new(0, line.size().1 as i32);
+        }
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let width = self.lines.iter().map(|l| l.size().0).max().unwrap_or(0);
+        let height = self.lines.iter().map(|l| l.size().1).sum();
+        (width, height)
+    }
+}
+
+fn get_texture_builder(
+    path: &Path,
+    backend: SvgBackend,
+    options: &RenderOptions,
+) -> Result<Box<dyn SvgTextureBuilder>, Error> {
+    if RasterImage::is_supported(path) {
+        return Ok(Box::new(RasterImage::new(path)?));
+    }
+    let builder: Box<dyn SvgTextureBuilder> = match backend {
+        #[cfg(feature = "use-rsvg")]
+        SvgBackend::RsvgWithCairo => Box::new(RsvgWithCairo::new(path, options)?),
+        #[cfg(feature = "use-usvg")]
+        SvgBackend::UsvgWithSkia => Box::new(UsvgWithSkia::new(path, options)?),
+    };
+    Ok(builder)
+}
+
+/// Point which stays in place when scale changes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ZoomAnchor {
+    /// Image point under the mouse cursor
+    Cursor,
+    /// Image point in the center of the viewport
+    Center,
+}
+
+/// Scale at which both images fit in given area
+fn fit_scale(
+    left_svg: &dyn SvgTextureBuilder,
+    right_svg: &dyn SvgTextureBuilder,
+    area: (u32, u32),
+) -> Result<f64, Error> {
+    let size = left_svg
+        .query_size(1.0)?
+        .union(right_svg.query_size(1.0)?)
+        .size();
+    Ok(f64::min(
+        f64::from(area.0) / f64::from(size.0),
+        f64::from(area.1) / f64::from(size.1),
+    ))
+}
+
+pub use cache_module::RenderCache;
+
+type BuilderPair = (Box<dyn SvgTextureBuilder>, Box<dyn SvgTextureBuilder>);
+
+/// Backends used for left and right file, same file rendered with different
+/// backends shows where they disagree
+pub type BackendPair = (SvgBackend, SvgBackend);
+
+/// Texture builder of the file which keeps rendered images in `cache`
+fn get_cached_builder(
+    path: &Path,
+    backend: SvgBackend,
+    options: &RenderOptions,
+    cache: &RenderCache,
+) -> Result<Box<dyn SvgTextureBuilder>, Error> {
+    let document = cache_module::DocumentKey::new(path, backend);
+    let builder = get_texture_builder(path, backend, options)?;
+    Ok(Box::new(cache_module::CachedBuilder::new(
+        builder, document, cache,
+    )))
+}
+
+/// Creates texture builders for both files of the pair, missing file is replaced
+/// with blank image of the same size as the other one
+fn get_pair_builders(
+    paths: (Option<&Path>, Option<&Path>),
+    backends: BackendPair,
+    options: &RenderOptions,
+    cache: &RenderCache,
+) -> Result<BuilderPair, Error> {
+    fn blank(other: &dyn SvgTextureBuilder) -> Result<Box<dyn SvgTextureBuilder>, Error> {
+        Ok(Box::new(Blank::with_size_of(other)?))
+    }
+
+    match paths {
+        (Some(left), Some(right)) => Ok((
+            get_cached_builder(left, backends.0, options, cache)?,
+            get_cached_builder(right, backends.1, options, cache)?,
+        )),
+        (Some(left), None) => {
+            let left = get_cached_builder(left, backends.0, options, cache)?;
+            let right = blank(left.as_ref())?;
+            Ok((left, right))
+        }
+        (None, Some(right)) => {
+            let right = get_cached_builder(right, backends.1, options, cache)?;
+            let left = blank(right.as_ref())?;
+            Ok((left, right))
+        }
+        (None, None) => Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "empty file pair",
+        ))),
+    }
+}
+
+/// Recreates texture builders of changed files. When one of the files is missing,
+/// both builders are recreated because blank image size depends on the other file.
+fn reload_pair_builders(
+    paths: (Option<&Path>, Option<&Path>),
+    backends: BackendPair,
+    options: &RenderOptions,
+    cache: &RenderCache,
+    builders: &mut BuilderPair,
+    changed: (bool, bool),
+) -> Result<(), Error> {
+    match paths {
+        (Some(left), Some(right)) => {
+            let left = changed
+                .0
+                .then(|| get_cached_builder(left, backends.0, options, cache))
+                .transpose()?;
+            let right = changed
+                .1
+                .then(|| get_cached_builder(right, backends.1, options, cache))
+                .transpose()?;
+            if let Some(left) = left {
+                builders.0 = left;
+            }
+            if let Some(right) = right {
+                builders.1 = right;
+            }
+        }
+        _ => *builders = get_pair_builders(paths, backends, options, cache)?,
+    }
+    Ok(())
+}
+
+/// Creates textures of rendered images and difference view
+fn create_views<'a>(
+    rendered: &render_module::Rendered,
+    texture_creator: &'a TextureCreator<WindowContext>,
+) -> Result<(Diff<'a>, DifferenceView<'a>), Error> {
+    let diff = Diff::new(
+        create_texture(texture_creator, &rendered.left)?,
+        create_texture(texture_creator, &rendered.right)?,
+        rendered.left_size,
+        rendered.right_size,
+        rendered.region,
+    );
+    let difference_view = DifferenceView::new(
+        create_texture(texture_creator, &rendered.difference)?,
+        diff.size(),
+        rendered.region,
+    );
+    Ok((diff, difference_view))
+}
+
+/// Part of the image, in image coordinates, shown in the viewport when image top
+/// left corner is placed at `position`
+fn visible_region(viewport: Rect, position: Point, side_by_side: bool) -> Rect {
+    if side_by_side {
+        // both halves show the same part of the image
+        let half_width = viewport.width() / 2;
+        let position = position - Point::new(half_width as i32 / 2, 0);
+        return Rect::new(
+            viewport.x() - position.x(),
+            viewport.y() - position.y(),
+            half_width,
+            viewport.height(),
+        );
+    }
+    Rect::new(
+        viewport.x() - position.x(),
+        viewport.y() - position.y(),
+        viewport.width(),
+        viewport.height(),
+    )
+}
+
+/// Part of the image which gets rendered into textures. Whole image is rendered
+/// when it fits in maximum texture size, otherwise area around `visible` region
+/// with some margin, so small pans do not require rendering again.
+fn render_region(size: (u32, u32), visible: Rect, max_texture_size: (u32, u32)) -> Rect {
+    if size.0 <= max_texture_size.0 && size.1 <= max_texture_size.1 {
+        return Rect::new(0, 0, size.0, size.1);
+    }
+    let width = cmp::min(cmp::min(visible.width() * 2, max_texture_size.0), size.0);
+    let height = cmp::min(cmp::min(visible.height() * 2, max_texture_size.1), size.1);
+    let x = (visible.center().x() - width as i32 / 2).clamp(0, (size.0 - width) as i32);
+    let y = (visible.center().y() - height as i32 / 2).clamp(0, (size.1 - height) as i32);
+    Rect::new(x, y, width, height)
+}
+
+/// Region to render when images at given scale get centered on `center`
+fn views_region(
+    left_svg: &dyn SvgTextureBuilder,
+    right_svg: &dyn SvgTextureBuilder,
+    scale: f64,
+    viewport: Rect,
+    center: Point,
+    side_by_side: bool,
+    max_texture_size: (u32, u32),
+) -> Result<Rect, Error> {
+    let size = left_svg
+        .query_size(scale)?
+        .union(right_svg.query_size(scale)?)
+        .size();
+    let position = center - Point::new(size.0 as i32 / 2, size.1 as i32 / 2);
+    let visible = visible_region(viewport, position, side_by_side);
+    Ok(render_region(size, visible, max_texture_size))
+}
+
+/// Checks if both images rendered at given scale fit in allowed size range
+fn check_size(
+    left_svg: &dyn SvgTextureBuilder,
+    right_svg: &dyn SvgTextureBuilder,
+    scale: f64,
+    min_size: (u32, u32),
+    max_size: (u32, u32),
+) -> Result<(), Error> {
+    let left_size = left_svg.query_size(scale)?.size();
+    let right_size = right_svg.query_size(scale)?.size();
+    debug!("Size at scale {}: {:?} {:?}", scale, left_size, right_size);
+
+    for size in [left_size, right_size] {
+        if size < min_size || size > max_size {
+            return Err(Error::SizeOutOfRange {
+                size,
+                min: min_size,
+                max: max_size,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Largest allowed size of rendered image, images exceeding maximum texture size
+/// are rendered only partially
+const MAX_IMAGE_SIZE: (u32, u32) = (1 << 20, 1 << 20);
+
+fn get_max_window_size(video_subsystem: &VideoSubsystem) -> Result<(u32, u32), Error> {
+    let bounds = {
+        let video_displays = video_subsystem.num_video_displays()?;
+        let mut max = Rect::new(0, 0, 0, 0);
+        for n in 0..video_displays {
+            let bounds = video_subsystem.display_usable_bounds(n)?;
+            let area = bounds.width() * bounds.height();
+            if area > max.width() * max.height() {
+                max = bounds;
+            }
+        }
+        max
+    };
+    debug!("Maximum display usable bounds: {:?}", bounds);
+    Ok(bounds.size())
+}
+
+fn screenshot<P: AsRef<Path>>(canvas: &Canvas<Window>, path: P) -> Result<(), Error> {
+    let rect = canvas.viewport();
+    let format = canvas.default_pixel_format();
+    let mut pixels = canvas.read_pixels(rect, format)?;
+
+    let screen = sdl2::surface::Surface::from_data(
+        &mut pixels,
+        rect.width(),
+        rect.height(),
+        4 * rect.width(),
+        format,
+    )?;
+
+    screen.save_bmp(path).map_err(Error::Display)
+}
+
+mod watch_module {
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    /// Detects file changes by polling its modification time
+    pub struct FileWatch {
+        path: Option<PathBuf>,
+        modified: Option<SystemTime>,
+    }
+
+    impl FileWatch {
+        pub fn new(path: Option<&Path>) -> FileWatch {
+            let mut watch = FileWatch {
+                path: path.map(|p| p.to_path_buf()),
+                modified: None,
+            };
+            watch.modified = watch.read_modified();
+            watch
+        }
+
+        fn read_modified(&self) -> Option<SystemTime> {
+            let path = self.path.as_ref()?;
+            fs::metadata(path).and_then(|m| m.modified()).ok()
+        }
+
+        /// Returns `true` if file changed since last call
+        pub fn changed(&mut self) -> bool {
+            if self.path.is_none() {
+                return false;
+            }
+            let modified = self.read_modified();
+            self.internal_update(modified)
+        }
+
+        fn internal_update(&mut self, modified: Option<SystemTime>) -> bool {
+            if modified != self.modified {
+                self.modified = modified;
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use std::time::Duration;
+
+        #[test]
+        fn test_internal_update() {
+            let mut w = FileWatch::new(None);
+            let t = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+            assert!(w.internal_update(Some(t)));
+            assert!(!w.internal_update(Some(t)));
+            assert!(w.internal_update(Some(t + Duration::from_secs(1))));
+            // file removed (for example during save) is a change as well
+            assert!(w.internal_update(None));
+            assert!(!w.internal_update(None));
+        }
+
+        #[test]
+        fn test_no_path() {
+            let mut w = FileWatch::new(None);
+            assert!(!w.changed());
+        }
+    }
+}
+
+mod cache_module {
+    use super::{Error, PixelBuffer, SvgBackend, SvgTextureBuilder};
+    use sdl2::rect::Rect;
+    use std::collections::VecDeque;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::time::SystemTime;
+
+    /// Identifies rendered document, modification time and length make sure
+    /// that images of files modified on disk are not reused
+    #[derive(Clone, PartialEq)]
+    pub struct DocumentKey {
+        path: PathBuf,
+        backend: SvgBackend,
+        modified: Option<SystemTime>,
+        len: u64,
+    }
+
+    impl DocumentKey {
+        pub fn new(path: &Path, backend: SvgBackend) -> DocumentKey {
+            let metadata = fs::metadata(path).ok();
+            DocumentKey {
+                path: path.to_path_buf(),
+                backend,
+                modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+                len: metadata.map_or(0, |m| m.len()),
+            }
+        }
+    }
+
+    /// Scale rounded so zooming in and out by irrational steps still hits the cache
+    type Key = (DocumentKey, i64, Rect);
+
+    fn key(document: &DocumentKey, scale: f64, region: Rect) -> Key {
+        (document.clone(), (scale * 1e6).round() as i64, region)
+    }
+
+    /// Least recently used rendered images, limited by total size of pixel data
+    struct Entries {
+        entries: VecDeque<(Key, PixelBuffer)>,
+        budget: usize,
+        used: usize,
+    }
+
+    impl Entries {
+        fn get(&mut self, key: &Key) -> Option<&PixelBuffer> {
+            let index = self.entries.iter().position(|(k, _)| k == key)?;
+            let entry = self.entries.remove(index)?;
+            self.entries.push_back(entry);
+            self.entries.back().map(|(_, buffer)| buffer)
+        }
+
+        fn insert(&mut self, key: Key, buffer: PixelBuffer) {
+            let size = buffer.data.len();
+            if size > self.budget {
+                return;
+            }
+            if let Some(index) = self.entries.iter().position(|(k, _)| *k == key) {
+                if let Some((_, old)) = self.entries.remove(index) {
+                    self.used -= old.data.len();
+                }
+            }
+            while self.used + size > self.budget {
+                match self.entries.pop_front() {
+                    Some((_, old)) => self.used -= old.data.len(),
+                    None => break,
+                }
+            }
+            self.used += size;
+            self.entries.push_back((key, buffer));
+        }
+    }
+
+    /// Recently rendered images of all documents limited to `budget` bytes of pixel
+    /// data. Clones share the same images, so headless comparison, the window and
+    /// the background worker render every file only once per scale. Cache must be
+    /// shared only by documents loaded with the same [`RenderOptions`](super::RenderOptions).
+    #[derive(Clone)]
+    pub struct RenderCache {
+        entries: Arc<Mutex<Entries>>,
+    }
+
+    impl RenderCache {
+        pub fn new(budget: usize) -> RenderCache {
+            RenderCache {
+                entries: Arc::new(Mutex::new(Entries {
+                    entries: VecDeque::new(),
+                    budget,
+                    used: 0,
+                })),
+            }
+        }
+
+        fn get(&self, document: &DocumentKey, scale: f64, region: Rect) -> Option<PixelBuffer> {
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            entries.get(&key(document, scale, region)).cloned()
+        }
+
+        fn insert(&self, document: &DocumentKey, scale: f64, region: Rect, buffer: PixelBuffer) {
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            entries.insert(key(document, scale, region), buffer);
+        }
+    }
+
+    /// Texture builder which keeps rendered images in shared cache, lock is not held
+    /// while rendering so the other thread is not blocked
+    pub struct CachedBuilder {
+        builder: Box<dyn SvgTextureBuilder>,
+        document: DocumentKey,
+        cache: RenderCache,
+    }
+
+    impl CachedBuilder {
+        pub fn new(
+            builder: Box<dyn SvgTextureBuilder>,
+            document: DocumentKey,
+            cache: &RenderCache,
+        ) -> CachedBuilder {
+            CachedBuilder {
+                builder,
+                document,
+                cache: cache.clone(),
+            }
+        }
+    }
+
+    impl SvgTextureBuilder for CachedBuilder {
+        fn query_size(&self, scale: f64) -> Result<Rect, Error> {
+            self.builder.query_size(scale)
+        }
+
+        fn warnings(&self) -> &[String] {
+            self.builder.warnings()
+        }
+
+        fn render_region(&self, scale: f64, region: Rect) -> Result<PixelBuffer, Error> {
+            if let Some(buffer) = self.cache.get(&self.document, scale, region) {
+                return Ok(buffer);
+            }
+            let buffer = self.builder.render_region(scale, region)?;
+            self.cache
+                .insert(&self.document, scale, region, buffer.clone());
+            Ok(buffer)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use clap::ValueEnum;
+
+        fn buffer(size: u32) -> PixelBuffer {
+            PixelBuffer {
+                width: size,
+                height: size,
+                data: vec![0; (size * size * 4) as usize],
+            }
+        }
+
+        fn document(path: &str) -> DocumentKey {
+            DocumentKey::new(Path::new(path), SvgBackend::value_variants()[0])
+        }
+
+        fn used(cache: &RenderCache) -> usize {
+            cache.entries.lock().unwrap().used
+        }
+
+        #[test]
+        fn test_get() {
+            let cache = RenderCache::new(1000);
+            let doc = document("tests/images/tinycircle01.svg");
+            let region = Rect::new(0, 0, 10, 10);
+            cache.insert(&doc, 1.0, region, buffer(10));
+            assert!(cache.get(&doc, 1.0, region).is_some());
+            assert!(cache.get(&doc, 2.0, region).is_none());
+            assert!(cache.get(&doc, 1.0, Rect::new(1, 0, 10, 10)).is_none());
+            // scale after zooming in and out by the same step
+            let scale = 1.0 * f64::sqrt(2.0) * f64::sqrt(2.0) / 2.0;
+            assert!(cache.get(&doc, scale, region).is_some());
+        }
+
+        #[test]
+        fn test_other_document() {
+            let cache = RenderCache::new(1000);
+            let doc = document("tests/images/tinycircle01.svg");
+            let region = Rect::new(0, 0, 10, 10);
+            cache.insert(&doc, 1.0, region, buffer(10));
+            assert!(cache
+                .get(&document("tests/images/arcs01.svg"), 1.0, region)
+                .is_none());
+            // shared by clones
+            assert!(cache.clone().get(&doc, 1.0, region).is_some());
+        }
+
+        #[test]
+        fn test_evict_least_recently_used() {
+            let cache = RenderCache::new(1000);
+            let doc = document("tests/images/tinycircle01.svg");
+            let region = Rect::new(0, 0, 10, 10);
+            cache.insert(&doc, 1.0, region, buffer(10));
+            cache.insert(&doc, 2.0, region, buffer(10));
+            assert!(cache.get(&doc, 1.0, region).is_some());
+            cache.insert(&doc, 3.0, region, buffer(10));
+            assert!(cache.get(&doc, 1.0, region).is_some());
+            assert!(cache.get(&doc, 2.0, region).is_none());
+            assert!(cache.get(&doc, 3.0, region).is_some());
+            assert_eq!(used(&cache), 800);
+        }
+
+        #[test]
+        fn test_over_budget() {
+            let cache = RenderCache::new(100);
+            let doc = document("tests/images/tinycircle01.svg");
+            let region = Rect::new(0, 0, 10, 10);
+            cache.insert(&doc, 1.0, region, buffer(10));
+            assert!(cache.get(&doc, 1.0, region).is_none());
+            assert_eq!(used(&cache), 0);
+        }
+    }
+}
+
+mod render_module {
+    use super::{
+        builder_warnings, compare_buffers, compare_pixels, difference, get_pair_builders,
+        reload_pair_builders, BackendPair, Blank, BuilderPair, Comparison, Error, PixelBuffer,
+        RenderCache, RenderOptions, SvgTextureBuilder, WarningPair,
+    };
+    use log::debug;
+    use sdl2::rect::Rect;
+    use std::cmp;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Files to render, `generation` has to change whenever files are replaced
+    /// or modified so worker loads them again
+    #[derive(Clone, PartialEq)]
+    pub struct Sources {
+        pub left: Option<PathBuf>,
+        pub right: Option<PathBuf>,
+        pub backends: BackendPair,
+        pub options: RenderOptions,
+        pub generation: u64,
+    }
+
+    impl Sources {
+        pub fn new(
+            paths: (Option<&Path>, Option<&Path>),
+            backends: BackendPair,
+            options: &RenderOptions,
+            generation: u64,
+        ) -> Sources {
+            Sources {
+                left: paths.0.map(|p| p.to_path_buf()),
+                right: paths.1.map(|p| p.to_path_buf()),
+                backends,
+                options: options.clone(),
+                generation,
+            }
+        }
+
+        fn paths(&self) -> (Option<&Path>, Option<&Path>) {
+            (self.left.as_deref(), self.right.as_deref())
+        }
+
+        /// Returns `true` when both refer to the same files rendered the same way,
+        /// regardless of their modifications
+        fn same_files(&self, other: &Sources) -> bool {
+            self.left == other.left
+                && self.right == other.right
+                && self.backends == other.backends
+                && self.options == other.options
+        }
+    }
+
+    enum Job {
+        Load {
+            id: u64,
+            sources: Sources,
+            changed: (bool, bool),
+        },
+        Render {
+            id: u64,
+            sources: Sources,
+            scale: f64,
+            region: Rect,
+        },
+    }
+
+    /// Loaded files, represented by blank images of the same size, so images
+    /// can be laid out without loading the files again
+    pub struct Loaded {
+        pub left: Blank,
+        pub right: Blank,
+        pub warnings: WarningPair,
+    }
+
+    impl Loaded {
+        fn new(builders: &BuilderPair) -> Result<Loaded, Error> {
+            let (left, right) = (builders.0.as_ref(), builders.1.as_ref());
+            Ok(Loaded {
+                left: Blank::with_size_of(left)?,
+                right: Blank::with_size_of(right)?,
+                warnings: builder_warnings(left, right),
+            })
+        }
+    }
+
+    /// Region of both images rendered at given scale
+    pub struct Rendered {
+        pub left: PixelBuffer,
+        pub right: PixelBuffer,
+        pub difference: PixelBuffer,
+        pub left_size: (u32, u32),
+        pub right_size: (u32, u32),
+        pub region: Rect,
+        /// Comparison of whole images when rendering is [`complete`](Rendered::complete),
+        /// otherwise comparison of rendered region without SSIM
+        pub comparison: Comparison,
+    }
+
+    impl Rendered {
+        /// Returns `true` when rendered region covers both images
+        pub fn complete(&self) -> bool {
+            let width = cmp::max(self.left_size.0, self.right_size.0);
+            let height = cmp::max(self.left_size.1, self.right_size.1);
+            self.region.contains_rect(Rect::new(0, 0, width, height))
+        }
+    }
+
+    /// Comparisons of whole images of loaded files by scale, so SSIM of the scale
+    /// is computed only once
+    #[derive(Default)]
+    pub struct Metrics {
+        comparisons: Vec<(i64, Comparison)>,
+    }
+
+    impl Metrics {
+        fn compare(&mut self, scale: f64, left: &PixelBuffer, right: &PixelBuffer) -> Comparison {
+            let key = (scale * 1e6).round() as i64;
+            if let Some((_, comparison)) = self.comparisons.iter().find(|(k, _)| *k == key) {
+                return comparison.clone();
+            }
+            let comparison = compare_buffers(left, right);
+            self.comparisons.push((key, comparison.clone()));
+            comparison
+        }
+    }
+
+    pub fn render(
+        left_svg: &dyn SvgTextureBuilder,
+        right_svg: &dyn SvgTextureBuilder,
+        scale: f64,
+        region: Rect,
+        metrics: &mut Metrics,
+    ) -> Result<Rendered, Error> {
+        let left = left_svg.render_region(scale, region)?;
+        let right = right_svg.render_region(scale, region)?;
+        let mut rendered = Rendered {
+            difference: difference(&left, &right),
+            comparison: compare_pixels(&left, &right, false),
+            left,
+            right,
+            left_size: left_svg.query_size(scale)?.size(),
+            right_size: right_svg.query_size(scale)?.size(),
+            region,
+        };
+        if rendered.complete() {
+            rendered.comparison = metrics.compare(scale, &rendered.left, &rendered.right);
+        }
+        Ok(rendered)
+    }
+
+    /// Result of a job done by [`RenderWorker`]
+    pub enum Update {
+        Loaded(Result<Loaded, Error>),
+        Rendered(Result<Rendered, Error>),
+    }
+
+    struct JobResult {
+        id: u64,
+        update: Update,
+    }
+
+    /// Loads and renders images on background thread. Render jobs which got
+    /// outdated before rendering started are skipped and only results of the latest
+    /// load and render jobs are reported. Rendered images are kept in `cache` shared
+    /// with the caller.
+    pub struct RenderWorker {
+        jobs: mpsc::Sender<Job>,
+        results: mpsc::Receiver<JobResult>,
+        last_id: u64,
+        last_load: Option<u64>,
+        last_render: Option<u64>,
+    }
+
+    impl RenderWorker {
+        pub fn new(cache: &RenderCache) -> RenderWorker {
+            let (jobs, job_receiver) = mpsc::channel();
+            let (result_sender, results) = mpsc::channel();
+            let cache = cache.clone();
+            thread::spawn(move || run(job_receiver, result_sender, cache));
+            RenderWorker {
+                jobs,
+                results,
+                last_id: 0,
+                last_load: None,
+                last_render: None,
+            }
+        }
+
+        /// Loads files of `sources`, only `changed` files when they were loaded
+        /// before with the same sources
+        pub fn load(&mut self, sources: &Sources, changed: (bool, bool)) {
+            self.last_id += 1;
+            self.last_load = Some(self.last_id);
+            let job = Job::Load {
+                id: self.last_id,
+                sources: sources.clone(),
+                changed,
+            };
+            if self.jobs.send(job).is_err() {
+                // worker thread is gone, there is nothing to wait for
+                self.last_load = None;
+            }
+        }
+
+        pub fn request(&mut self, sources: &Sources, scale: f64, region: Rect) {
+            self.last_id += 1;
+            self.last_render = Some(self.last_id);
+            let job = Job::Render {
+                id: self.last_id,
+                sources: sources.clone(),
+                scale,
+                region,
+            };
+            if self.jobs.send(job).is_err() {
+                self.last_render = None;
+            }
+        }
+
+        /// Returns `true` when result of the latest load job was not received yet
+        pub fn loading(&self) -> bool {
+            self.last_load.is_some()
+        }
+
+        /// Returns `true` when result of the latest load or render job was not
+        /// received yet
+        pub fn pending(&self) -> bool {
+            self.last_load.is_some() || self.last_render.is_some()
+        }
+
+        /// Returns result of the latest load or render job if it is ready
+        pub fn poll(&mut self) -> Option<Update> {
+            while let Ok(result) = self.results.try_recv() {
+                if let Some(update) = self.latest(result) {
+                    return Some(update);
+                }
+            }
+            None
+        }
+
+        /// Waits for result of the latest load or render job, returns `None` when
+        /// there is no pending job
+        pub fn wait(&mut self) -> Option<Update> {
+            while self.pending() {
+                let result = self.results.recv().ok()?;
+                if let Some(update) = self.latest(result) {
+                    return Some(update);
+                }
+            }
+            None
+        }
+
+        fn latest(&mut self, result: JobResult) -> Option<Update> {
+            let last = match result.update {
+                Update::Loaded(_) => &mut self.last_load,
+                Update::Rendered(_) => &mut self.last_render,
+            };
+            if *last != Some(result.id) {
+                return None;
+            }
+            *last = None;
+            Some(result.update)
+        }
+    }
+
+    fn run(jobs: mpsc::Receiver<Job>, results: mpsc::Sender<JobResult>, cache: RenderCache) {
+        let mut loaded: Option<(Sources, BuilderPair)> = None;
+        let mut metrics = Metrics::default();
+        while let Ok(job) = jobs.recv() {
+            let queue: Vec<Job> = std::iter::once(job).chain(jobs.try_iter()).collect();
+            let last_render = queue
+                .iter()
+                .rposition(|job| matches!(job, Job::Render { .. }));
+            for (index, job) in queue.into_iter().enumerate() {
+                let result = match job {
+                    Job::Load {
+                        id,
+                        sources,
+                        changed,
+                    } => {
+                        debug!("Loading job {}", id);
+                        metrics = Metrics::default();
+                        let builders = match loaded.take() {
+                            Some((previous, mut builders)) if previous.same_files(&sources) => {
+                                reload_pair_builders(
+                                    sources.paths(),
+                                    sources.backends,
+                                    &sources.options,
+                                    &cache,
+                                    &mut builders,
+                                    changed,
+                                )
+                                .map(|_| builders)
+                            }
+                            _ => get_pair_builders(
+                                sources.paths(),
+                                sources.backends,
+                                &sources.options,
+                                &cache,
+                            ),
+                        };
+                        let update = builders.and_then(|builders| {
+                            let update = Loaded::new(&builders);
+                            loaded = Some((sources, builders));
+                            update
+                        });
+                        JobResult {
+                            id,
+                            update: Update::Loaded(update),
+                        }
+                    }
+                    Job::Render {
+                        id,
+                        sources,
+                        scale,
+                        region,
+                    } if Some(index) == last_render => {
+                        debug!("Rendering job {} at scale {}", id, scale);
+                        let rendered = render_sources(
+                            &mut loaded,
+                            sources,
+                            scale,
+                            region,
+                            &mut metrics,
+                            &cache,
+                        );
+                        JobResult {
+                            id,
+                            update: Update::Rendered(rendered),
+                        }
+                    }
+                    // outdated by the latest render job
+                    Job::Render { .. } => continue,
+                };
+                if results.send(result).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Renders `sources`, which get loaded first when other files are loaded
+    fn render_sources(
+        loaded: &mut Option<(Sources, BuilderPair)>,
+        sources: Sources,
+        scale: f64,
+        region: Rect,
+        metrics: &mut Metrics,
+        cache: &RenderCache,
+    ) -> Result<Rendered, Error> {
+        if loaded.as_ref().map(|(s, _)| s) != Some(&sources) {
+            *metrics = Metrics::default();
+            *loaded = None;
+            let builders =
+                get_pair_builders(sources.paths(), sources.backends, &sources.options, cache)?;
+            *loaded = Some((sources, builders));
+        }
+        let (_, (left, right)) = loaded.as_ref().expect("files are loaded");
+        render(left.as_ref(), right.as_ref(), scale, region, metrics)
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::{get_texture_builder, SvgBackend};
+        use clap::ValueEnum;
+        use std::time::Duration;
+
+        fn sources(path: &str, generation: u64) -> Sources {
+            let path = Path::new(path);
+            let backend = SvgBackend::value_variants()[0];
+            Sources::new(
+                (Some(path), Some(path)),
+                (backend, backend),
+                &RenderOptions::default(),
+                generation,
+            )
+        }
+
+        #[test]
+        fn test_latest_result() {
+            let sources = sources("tests/images/tinycircle01.svg", 0);
+            let mut worker = RenderWorker::new(&RenderCache::new(1 << 20));
+            worker.request(&sources, 1.0, Rect::new(0, 0, 10, 10));
+            worker.request(&sources, 2.0, Rect::new(0, 0, 20, 20));
+            assert!(worker.pending());
+
+            let rendered = loop {
+                match worker.poll() {
+                    Some(Update::Rendered(rendered)) => break rendered.unwrap(),
+                    Some(Update::Loaded(_)) => panic!("no files were requested to load"),
+                    None => thread::sleep(Duration::from_millis(10)),
+                }
+            };
+            assert!(!worker.pending());
+            assert_eq!(rendered.region, Rect::new(0, 0, 20, 20));
+            assert_eq!(rendered.left.width, 20);
+            assert_eq!(rendered.left_size, rendered.right_size);
+        }
+
+        #[test]
+        fn test_load() {
+            let mut worker = RenderWorker::new(&RenderCache::new(1 << 20));
+            worker.load(&sources("tests/images/tinycircle01.svg", 0), (true, true));
+            // only result of the latest load is reported
+            worker.load(&sources("tests/images/arcs01.svg", 1), (true, true));
+            assert!(worker.loading());
+
+            let Some(Update::Loaded(loaded)) = worker.wait() else {
+                panic!("files were not loaded");
+            };
+            let loaded = loaded.unwrap();
+            assert!(!worker.loading());
+            assert!(worker.wait().is_none());
+            let document = get_texture_builder(
+                Path::new("tests/images/arcs01.svg"),
+                SvgBackend::value_variants()[0],
+                &RenderOptions::default(),
+            )
+            .unwrap();
+            assert_eq!(
+                loaded.left.query_size(1.0).unwrap(),
+                document.query_size(1.0).unwrap()
+            );
+            assert!(loaded.warnings.0.is_empty());
+        }
+    }
+}
+
+mod blink_module {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use super::Side;
+
+    pub struct Blink {
+        interval: Duration,
+        side: Side,
+        paused: bool,
+        last_switch: Instant,
+    }
+
+    impl Blink {
+        pub fn new(interval: Duration) -> Blink {
+            Blink {
+                interval,
+                side: Side::Left,
+                paused: false,
+                last_switch: Instant::now(),
+            }
+        }
+
+        pub fn update(&mut self) {
+            self.internal_update(Instant::now())
+        }
+
+        fn internal_update(&mut self, now: Instant) {
+            if !self.paused && now.duration_since(self.last_switch) >= self.interval {
+                self.side = match self.side {
+                    Side::Left => Side::Right,
+                    Side::Right => Side::Left,
+                };
+                self.last_switch = now;
+            }
+        }
+
+        pub fn toggle_pause(&mut self) {
+            self.paused = !self.paused;
+            self.last_switch = Instant::now();
+        }
+
+        /// Stops alternating and keeps showing given side
+        pub fn pause_on(&mut self, side: Side) {
+            self.side = side;
+            self.paused = true;
+        }
+
+        pub fn side(&self) -> Side {
+            self.side
+        }
+
+        pub fn paused(&self) -> bool {
+            self.paused
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_internal_update() {
+            let mut b = Blink::new(Duration::from_millis(500));
+            let start = b.last_switch;
+            b.internal_update(start + Duration::from_millis(499));
+            assert_eq!(b.side(), Side::Left);
+            b.internal_update(start + Duration::from_millis(500));
+            assert_eq!(b.side(), Side::Right);
+            // interval is measured from the last switch
+            b.internal_update(start + Duration::from_millis(900));
+            assert_eq!(b.side(), Side::Right);
+            b.internal_update(start + Duration::from_millis(1000));
+            assert_eq!(b.side(), Side::Left);
+        }
+
+        #[test]
+        fn test_pause() {
+            let mut b = Blink::new(Duration::from_millis(500));
+            b.pause_on(Side::Right);
+            b.internal_update(b.last_switch + Duration::from_secs(10));
+            assert!(b.paused());
+            assert_eq!(b.side(), Side::Right);
+            b.toggle_pause();
+            assert!(!b.paused());
+            b.internal_update(b.last_switch + Duration::from_millis(500));
+            assert_eq!(b.side(), Side::Left);
+        }
+    }
+}
+
+mod drag_module {
+    use sdl2::mouse::MouseButton;
+    use sdl2::mouse::MouseState;
+    use sdl2::rect::Point;
+
+    pub struct Drag {
+        active: bool,
+        drag_start: Point,
+        drag: Point,
+    }
+
+    impl Drag {
+        pub fn new() -> Drag {
+            let drag_start = Point::new(0, 0);
+            let drag = Point::new(0, 0);
+            Drag {
+                active: false,
+                drag_start,
+                drag,
+            }
+        }
+
+        pub fn reset(&mut self) {
+            self.active = false;
+            self.drag_start = Point::new(0, 0);
+            self.drag = Point::new(0, 0);
+        }
+
+        pub fn update(&mut self, state: &MouseState) {
+            let pressed = state.is_mouse_button_pressed(MouseButton::Right);
+            self.internal_update(pressed, state.x(), state.y())
+        }
+
+        fn internal_update(&mut self, pressed: bool, x: i32, y: i32) {
+            if pressed {
+                let position = Point::new(x, y);
+                if !self.active {
+                    self.drag_start = position - self.drag;
+                    self.active = true;
+                } else {
+                    self.drag = position - self.drag_start;
+                }
+            } else {
+                self.active = false;
+            }
+        }
+
+        pub fn get(&self) -> Point {
+            self.drag
+        }
+
+        /// Sets new drag value, works also while dragging is active
+        pub fn set(&mut self, drag: Point) {
+            self.drag_start += self.drag - drag;
+            self.drag = drag;
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        fn assert_state(d: &Drag, active: bool, start_x: i32, start_y: i32, x: i32, y: i32) {
+            assert_eq!(d.active, active);
+            assert_eq!(d.drag_start, Point::new(start_x, start_y));
+            assert_eq!(d.drag, Point::new(x, y));
+        }
+
+        #[test]
+        fn test_reset() {
+            let mut d = Drag::new();
+            d.drag_start = Point::new(10, 20);
+            d.drag = Point::new(30, 40);
+            d.reset();
+            assert_state(&d, false, 0, 0, 0, 0);
+        }
+
+        #[test]
+        fn test_internal_update() {
+            let mut d = Drag::new();
+            d.internal_update(true, 10, 10);
+            // first update when pressed true does not change drag value, just toggles the state
+            assert_state(&d, true, 10, 10, 0, 0);
+            // the second update with pressed calculates new drag
+            d.internal_update(true, 15, 20);
+            assert_state(&d, true, 10, 10, 5, 10);
+            // releasing button does not reset drag, just clears the start state
+            d.internal_update(false, 20, 20);
+            assert_state(&d, false, 10, 10, 5, 10);
+            // starting all over, changing start drag position to keep continuous dragging
+            d.internal_update(true, 0, 0);
+            assert_state(&d, true, -5, -10, 5, 10);
+            d.internal_update(true, 5, 0);
+            assert_state(&d, true, -5, -10, 10, 10);
+        }
+
+        #[test]
+        fn test_set() {
+            let mut d = Drag::new();
+            d.set(Point::new(10, 20));
+            assert_state(&d, false, -10, -20, 10, 20);
+            // setting while dragging keeps dragging continuous
+            d.internal_update(true, 0, 0);
+            assert_state(&d, true, -10, -20, 10, 20);
+            d.set(Point::new(0, 0));
+            assert_state(&d, true, 0, 0, 0, 0);
+            d.internal_update(true, 5, 5);
+            assert_state(&d, true, 0, 0, 5, 5);
+        }
+    }
+}
+
+/// File to compare. It is either a path on disk or `<rev>:<path>` git object
+/// specification, in which case the blob is read from git repository in current
+/// directory and stored in temporary file removed on drop.
+pub struct InputFile {
+    path: PathBuf,
+    label: String,
+    /// Private directory holding the temporary file
+    temporary_dir: Option<PathBuf>,
+}
+
+/// Checks if missing path is `<rev>:<path>` git object specification,
+/// Windows paths starting with drive letter like `C:\x.svg` are not
+fn is_git_spec(path: &str) -> bool {
+    match path.split_once(':') {
+        Some((prefix, _)) => {
+            !(prefix.len() == 1 && prefix.chars().all(|c| c.is_ascii_alphabetic()))
+        }
+        None => false,
+    }
+}
+
+/// Creates new directory accessible only by current user in system temporary
+/// directory, so files written there can't be replaced or redirected by others
+fn create_private_dir() -> std::io::Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    for _ in 0..100 {
+        let dir = env::temp_dir().join(format!(
+            "lukaj-{}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        // fails also when symbolic link of the same name exists
+        match builder.create(&dir) {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|_| dir),
+        }
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        "could not create temporary directory",
+    ))
+}
+
+impl InputFile {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<InputFile, Error> {
+        let path = path.as_ref();
+        let spec = path.to_str().filter(|p| !path.exists() && is_git_spec(p));
+        match spec {
+            Some(spec) => InputFile::from_git(spec),
+            None => Ok(InputFile {
+                path: path.to_path_buf(),
+                label: path.display().to_string(),
+                temporary_dir: None,
+            }),
+        }
+    }
+
+    fn from_git(spec: &str) -> Result<InputFile, Error> {
+        let output = Command::new("git")
+            .args(["cat-file", "blob", spec])
+            .output()
+            .map_err(|e| std::io::Error::new(e.kind(), format!("failed to run git: {}", e)))?;
+        if !output.status.success() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "failed to read {:?} from git repository: {}",
+                    spec,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            )));
+        }
+
+        // keep original file name, file extension is used for selecting image loader
+        let file_name = spec
+            .rsplit(':')
+            .next()
+            .and_then(|p| Path::new(p).file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("blob");
+        let dir = create_private_dir()?;
+        let path = dir.join(file_name);
+        let input = InputFile {
+            path,
+            label: spec.to_string(),
+            temporary_dir: Some(dir),
+        };
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&input.path)?
+            .write_all(&output.stdout)?;
+        debug!("Git object {:?} written to {:?}", spec, input.path);
+        Ok(input)
+    }
+
+    /// Sets name displayed instead of file path, useful when running as git
+    /// difftool which passes temporary files
+    pub fn with_label(mut self, label: String) -> InputFile {
+        self.label = label;
+        self
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+impl AsRef<Path> for InputFile {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for InputFile {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.temporary_dir {
+            let _ = fs::remove_file(&self.path);
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChangeStatus {
+    Added,
+    Removed,
+    Modified,
+    Unchanged,
+}
+
+impl ChangeStatus {
+    fn name(&self) -> &'static str {
+        match self {
+            ChangeStatus::Added => "added",
+            ChangeStatus::Removed => "removed",
+            ChangeStatus::Modified => "modified",
+            ChangeStatus::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// Pair of files to compare. When comparing directories one of the files
+/// might be missing.
+pub struct FilePair {
+    pub left: Option<InputFile>,
+    pub right: Option<InputFile>,
+    pub status: ChangeStatus,
+    /// Backend warnings found while comparing directories
+    pub warnings: WarningPair,
+}
+
+impl FilePair {
+    /// Creates pair of two existing files, files are unchanged when they are
+    /// identical or XML equivalent
+    pub fn new(left: InputFile, right: InputFile) -> Result<FilePair, Error> {
+        let status = if Equality::of_files(left.as_ref(), right.as_ref())?.is_some() {
+            ChangeStatus::Unchanged
+        } else {
+            ChangeStatus::Modified
+        };
+        Ok(FilePair {
+            left: Some(left),
+            right: Some(right),
+            status,
+            warnings: WarningPair::default(),
+        })
+    }
+
+    /// Name of the pair displayed in message bar and directory comparison summary
+    pub fn label(&self) -> String {
+        fn label(file: &Option<InputFile>) -> &str {
+            file.as_ref().map(|f| f.label()).unwrap_or("-")
+        }
+        format!(
+            "Left: {:?} Right: {:?}",
+            label(&self.left),
+            label(&self.right)
+        )
+    }
+
+    pub fn status_name(&self) -> &'static str {
+        self.status.name()
+    }
+
+    fn paths(&self) -> (Option<&Path>, Option<&Path>) {
+        (
+            self.left.as_ref().map(|f| f.as_ref()),
+            self.right.as_ref().map(|f| f.as_ref()),
+        )
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    let svg = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("svg") || e.eq_ignore_ascii_case("svgz"))
+        .unwrap_or(false);
+    svg || RasterImage::is_supported(path)
+}
+
+/// Collects paths (relative to `root`) of all images in `dir` and its subdirectories
+fn collect_images(root: &Path, dir: &Path, images: &mut BTreeSet<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_images(root, &path, images)?;
+        } else if is_image(&path) {
+            if let Ok(relative) = path.strip_prefix(root) {
+                images.insert(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Warnings of files of the pair, file which can't be loaded reports the error
+fn file_warnings(
+    paths: (Option<&Path>, Option<&Path>),
+    backends: BackendPair,
+    options: &RenderOptions,
+) -> WarningPair {
+    let warnings = |path: Option<&Path>, backend| match path {
+        Some(path) => match get_texture_builder(path, backend, options) {
+            Ok(builder) => builder.warnings().to_vec(),
+            Err(e) => vec![e.to_string()],
+        },
+        None => Vec::new(),
+    };
+    (warnings(paths.0, backends.0), warnings(paths.1, backends.1))
+}
+
+/// Pairs images of two directories by relative path and returns pairs which
+/// are added, removed or modified. Files are considered modified when they
+/// are not XML equivalent and their rasterized images differ by more than `tolerance`.
+/// Images rendered for comparison are kept in `cache` for reuse by [`app`].
+pub fn diff_directories(
+    left_dir: &Path,
+    right_dir: &Path,
+    scale: f64,
+    backends: BackendPair,
+    options: &RenderOptions,
+    cache: &RenderCache,
+    tolerance: u8,
+) -> Result<Vec<FilePair>, Error> {
+    let mut left_images = BTreeSet::new();
+    let mut right_images = BTreeSet::new();
+    collect_images(left_dir, left_dir, &mut left_images)?;
+    collect_images(right_dir, right_dir, &mut right_images)?;
+
+    let mut pairs = Vec::new();
+    for relative in left_images.union(&right_images) {
+        let left = left_dir.join(relative);
+        let right = right_dir.join(relative);
+        let (status, warnings) = match (
+            left_images.contains(relative),
+            right_images.contains(relative),
+        ) {
+            (true, false) => (
+                ChangeStatus::Removed,
+                file_warnings((Some(&left), None), backends, options),
+            ),
+            (false, true) => (
+                ChangeStatus::Added,
+                file_warnings((None, Some(&right)), backends, options),
+            ),
+            _ => {
+                if backends.0 == backends.1 && Equality::of_files(&left, &right)?.is_some() {
+                    continue;
+                }
+                match compare(&left, &right, scale, backends, options, cache, None) {
+                    Ok((comparison, _))
+                        if Equality::of_rendering(&comparison, tolerance)
+                            != Equality::Different =>
+                    {
+                        continue
+                    }
+                    Ok((_, warnings)) => (ChangeStatus::Modified, warnings),
+                    Err(e) => {
+                        warn!("Could not compare {:?}: {}", relative, e);
+                        let paths = (Some(left.as_path()), Some(right.as_path()));
+                        (
+                            ChangeStatus::Modified,
+                            file_warnings(paths, backends, options),
+                        )
+                    }
+                }
+            }
+        };
+        debug!("{:?}: {}", relative, status.name());
+
+        let left = match status {
+            ChangeStatus::Added => None,
+            _ => Some(InputFile::new(&left)?),
+        };
+        let right = match status {
+            ChangeStatus::Removed => None,
+            _ => Some(InputFile::new(&right)?),
+        };
+        pairs.push(FilePair {
+            left,
+            right,
+            status,
+            warnings,
+        });
+    }
+    Ok(pairs)
+}
+
+/// Image loaded with one of the backends (raster images are loaded regardless
+/// of backend), can be rendered without opening a window:
+///
+/// ```no_run
+/// use lukaj::{compare_buffers, Document, SvgBackend};
+/// use clap::ValueEnum;
+///
+/// let backend = SvgBackend::value_variants()[0];
+/// let left = Document::load("left.svg", backend)?.render(2.0)?;
+/// let right = Document::load("right.svg", backend)?.render(2.0)?;
+/// assert!(compare_buffers(&left, &right).identical());
+/// # Ok::<(), lukaj::Error>(())
+/// ```
+pub struct Document {
+    builder: Box<dyn SvgTextureBuilder>,
+}
+
+impl Document {
+    pub fn load<P: AsRef<Path>>(path: P, backend: SvgBackend) -> Result<Document, Error> {
+        Document::load_with_options(path, backend, &RenderOptions::default())
+    }
+
+    /// Documents loaded with clones of the same options share loaded fonts
+    pub fn load_with_options<P: AsRef<Path>>(
+        path: P,
+        backend: SvgBackend,
+        options: &RenderOptions,
+    ) -> Result<Document, Error> {
+        Ok(Document {
+            builder: get_texture_builder(path.as_ref(), backend, options)?,
+        })
+    }
+
+    /// Size in pixels of the document rendered at given scale
+    pub fn size(&self, scale: f64) -> Result<(u32, u32), Error> {
+        Ok(self.builder.query_size(scale)?.size())
+    }
+
+    pub fn render(&self, scale: f64) -> Result<PixelBuffer, Error> {
+        self.builder.render(scale)
+    }
+
+    /// Warnings reported by backend while loading, collected only when logger
+    /// was installed with [`init_logger`]
+    pub fn warnings(&self) -> &[String] {
+        self.builder.warnings()
+    }
+}
+
+pub fn diff_files(left: &Path, right: &Path) -> Result<bool, std::io::Error> {
+    let f1 = File::open(left)?;
+    let f2 = File::open(right)?;
+
+    // Check if file sizes are different
+    if f1.metadata()?.len() != f2.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let mut f1 = BufReader::new(f1);
+    let mut f2 = BufReader::new(f2);
+
+    let mut buf1 = Vec::new();
+    let mut buf2 = Vec::new();
+
+    // probably naive but we are not expecting large files
+
+    f1.read_to_end(&mut buf1)?;
+    f2.read_to_end(&mut buf2)?;
+
+    for (a, b) in buf1.iter().zip(buf2.iter()) {
+        if a != b {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Parses XML document, DTD is allowed because SVG files often include it
+fn parse_xml(text: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    roxmltree::Document::parse_with_options(text, options)
+}
+
+/// Significance of whitespace in text nodes
+#[derive(Copy, Clone, PartialEq)]
+enum Whitespace {
+    /// Formatting between elements, only the words matter
+    Ignorable,
+    /// Text content, runs of whitespace are rendered as a single space
+    Collapse,
+    /// Content with `xml:space="preserve"`
+    Preserve,
+}
+
+/// Appends canonical form of children of `node` to `out`: attributes are sorted,
+/// whitespace is normalized according to its significance and comments and
+/// processing instructions are skipped
+fn canonical_xml(node: roxmltree::Node, whitespace: Whitespace, out: &mut String) {
+    for child in node.children() {
+        if child.is_element() {
+            let name = child.tag_name();
+            let whitespace = match child.attribute((roxmltree::NS_XML_URI, "space")) {
+                Some("preserve") => Whitespace::Preserve,
+                None if whitespace == Whitespace::Preserve => Whitespace::Preserve,
+                _ if name.name() == "text" || whitespace != Whitespace::Ignorable => {
+                    Whitespace::Collapse
+                }
+                _ => Whitespace::Ignorable,
+            };
+            let mut attributes: Vec<_> = child
+                .attributes()
+                .map(|a| (a.namespace().unwrap_or(""), a.name(), a.value()))
+                .collect();
+            attributes.sort();
+            out.push_str(&format!(
+                "<{{{}}}{}",
+                name.namespace().unwrap_or(""),
+                name.name()
+            ));
+            for (namespace, name, value) in attributes {
+                out.push_str(&format!(" {{{}}}{}={:?}", namespace, name, value));
+            }
+            out.push('>');
+            canonical_xml(child, whitespace, out);
+            out.push_str("</>");
+        } else if child.is_text() {
+            let text = child.text().unwrap_or("");
+            let text = match whitespace {
+                Whitespace::Ignorable => text.split_whitespace().collect::<Vec<_>>().join(" "),
+                Whitespace::Collapse => collapse_whitespace(text),
+                Whitespace::Preserve => text.to_string(),
+            };
+            if !text.is_empty() {
+                out.push_str(&format!("{:?}", text));
+            }
+        }
     }
 }
 
-struct MessageBar<'a> {
-    message: SimpleCanvasEntity<'a>,
+/// Replaces runs of whitespace with a single space, leading and trailing
+/// whitespace is kept as it separates words of adjacent text nodes
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if !c.is_whitespace() {
+            out.push(c);
+        } else if !out.ends_with(' ') {
+            out.push(' ');
+        }
+    }
+    out
 }
 
-impl<'a> MessageBar<'a> {
-    fn new(
-        text: &str,
-        font: &Font,
-        texture_creator: &'a TextureCreator<WindowContext>,
-    ) -> Result<MessageBar<'a>, String> {
-        Ok(MessageBar {
-            message: new_static_text(text, font, texture_creator)?,
-        })
-    }
+fn canonical_xml_of(path: &Path) -> Result<Option<String>, std::io::Error> {
+    let Ok(text) = String::from_utf8(fs::read(path)?) else {
+        return Ok(None);
+    };
+    Ok(parse_xml(&text).ok().map(|document| {
+        let mut out = String::new();
+        canonical_xml(document.root(), Whitespace::Ignorable, &mut out);
+        out
+    }))
 }
 
-impl<'a> CanvasEntity for MessageBar<'a> {
-    fn draw(&self, renderer: &mut sdl2::render::WindowCanvas) -> Result<(), String> {
-        self.message.draw(renderer)?;
-        Ok(())
-    }
+/// Checks if two files are the same XML document regardless of attribute order,
+/// formatting and comments. Files which are not valid XML are never equivalent.
+pub fn xml_equivalent(left: &Path, right: &Path) -> Result<bool, std::io::Error> {
+    Ok(match (canonical_xml_of(left)?, canonical_xml_of(right)?) {
+        (Some(left), Some(right)) => left == right,
+        _ => false,
+    })
+}
 
-    fn reposition(&mut self, position: Point) {
-        self.message.reposition(position)
+/// Result of pixel by pixel comparison of two images
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub left_size: (u32, u32),
+    pub right_size: (u32, u32),
+    /// Number of differing pixels, when sizes differ area covered only by one
+    /// of the images is compared with transparent pixels
+    pub changed_pixels: usize,
+    /// Largest difference of a single color channel value
+    pub max_difference: u8,
+    /// Peak signal-to-noise ratio in dB, infinite for identical images
+    pub psnr: f64,
+    /// Structural similarity, 1.0 for identical images. Not available for
+    /// images too small to compute it.
+    pub ssim: Option<f64>,
+}
+
+impl Comparison {
+    /// Number of compared pixels, area covering both images
+    pub fn total_pixels(&self) -> usize {
+        let width = cmp::max(self.left_size.0, self.right_size.0) as usize;
+        let height = cmp::max(self.left_size.1, self.right_size.1) as usize;
+        width * height
     }
 
-    fn size(&self) -> (u32, u32) {
-        self.message.size()
+    /// Fraction of changed pixels
+    pub fn changed_ratio(&self) -> f64 {
+        self.changed_pixels as f64 / cmp::max(self.total_pixels(), 1) as f64
     }
-}
 
-fn get_texture_builder<'a>(
-    path: &Path,
-    backend: SvgBackend,
-) -> Result<Box<dyn SvgTextureBuilder<'a>>, String> {
-    let builder: Box<dyn SvgTextureBuilder> = match backend {
-        #[cfg(feature = "use-rsvg")]
-        SvgBackend::RsvgWithCairo => Box::new(RsvgWithCairo::new(path)?),
-        #[cfg(feature = "use-usvg")]
-        SvgBackend::UsvgWithSkia => Box::new(UsvgWithSkia::new(path)?),
-    };
-    Ok(builder)
+    pub fn identical(&self) -> bool {
+        self.left_size == self.right_size && self.changed_pixels == 0
+    }
 }
 
-fn get_max_window_size(video_subsystem: &VideoSubsystem) -> Result<(u32, u32), String> {
-    let bounds = {
-        let video_displays = video_subsystem.num_video_displays()?;
-        let mut max = Rect::new(0, 0, 0, 0);
-        for n in 0..video_displays {
-            let bounds = video_subsystem.display_usable_bounds(n)?;
-            let area = bounds.width() * bounds.height();
-            if area > max.width() * max.height() {
-                max = bounds;
-            }
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "changed: {} ({:.2}%), max difference: {}, PSNR: {:.2} dB, SSIM: ",
+            self.changed_pixels,
+            100.0 * self.changed_ratio(),
+            self.max_difference,
+            self.psnr
+        )?;
+        match self.ssim {
+            Some(ssim) => write!(f, "{:.4}", ssim),
+            None => write!(f, "-"),
         }
-        max
-    };
-    debug!("Maximum display usable bounds: {:?}", bounds);
-    Ok(bounds.size())
+    }
 }
 
-fn screenshot<P: AsRef<Path>>(canvas: &Canvas<Window>, path: P) -> Result<(), String> {
-    let rect = canvas.viewport();
-    let format = canvas.default_pixel_format();
-    let mut pixels = canvas.read_pixels(rect, format)?;
+/// Level of equality between two files, from the strongest to none
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Equality {
+    /// Files are byte-wise identical
+    Identical,
+    /// Files differ only in attribute order, formatting or comments
+    XmlEquivalent,
+    /// Rendered images are identical
+    PixelIdentical,
+    /// Rendered images differ by no more than allowed tolerance
+    WithinTolerance,
+    Different,
+}
 
-    let screen = sdl2::surface::Surface::from_data(
-        &mut pixels,
-        rect.width(),
-        rect.height(),
-        4 * rect.width(),
-        format,
-    )?;
+impl Equality {
+    /// Checks equality levels which do not require rendering,
+    /// `None` when files are neither identical nor XML equivalent
+    pub fn of_files(left: &Path, right: &Path) -> Result<Option<Equality>, std::io::Error> {
+        Ok(if diff_files(left, right)? {
+            Some(Equality::Identical)
+        } else if xml_equivalent(left, right)? {
+            Some(Equality::XmlEquivalent)
+        } else {
+            None
+        })
+    }
 
-    screen.save_bmp(path)
+    /// Equality of rendered images, `tolerance` is the maximum allowed difference
+    /// of a color channel value
+    pub fn of_rendering(comparison: &Comparison, tolerance: u8) -> Equality {
+        if comparison.identical() {
+            Equality::PixelIdentical
+        } else if comparison.left_size == comparison.right_size
+            && comparison.max_difference <= tolerance
+        {
+            Equality::WithinTolerance
+        } else {
+            Equality::Different
+        }
+    }
 }
 
-mod drag_module {
-    use sdl2::mouse::MouseButton;
-    use sdl2::mouse::MouseState;
-    use sdl2::rect::Point;
-
-    pub struct Drag {
-        active: bool,
-        drag_start: Point,
-        drag: Point,
+impl fmt::Display for Equality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Equality::Identical => "files are identical",
+            Equality::XmlEquivalent => "files are XML equivalent",
+            Equality::PixelIdentical => "rendered images are identical",
+            Equality::WithinTolerance => "rendered images are identical within tolerance",
+            Equality::Different => "images differ",
+        })
     }
+}
 
-    impl Drag {
-        pub fn new() -> Drag {
-            let drag_start = Point::new(0, 0);
-            let drag = Point::new(0, 0);
-            Drag {
-                active: false,
-                drag_start,
-                drag,
-            }
-        }
+/// Pixels of the area covering both images, converted for SSIM computation
+fn unpremultiplied(buffer: &PixelBuffer, width: u32, height: u32) -> Vec<rgb::RGBA8> {
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| buffer.pixel(x, y)))
+        .map(|[r, g, b, a]| {
+            let unpremultiply = |c: u8| match a {
+                0 => 0,
+                a => (u16::from(c) * 255 / u16::from(a)) as u8,
+            };
+            rgb::RGBA8::new(unpremultiply(r), unpremultiply(g), unpremultiply(b), a)
+        })
+        .collect()
+}
 
-        pub fn reset(&mut self) {
-            self.active = false;
-            self.drag_start = Point::new(0, 0);
-            self.drag = Point::new(0, 0);
-        }
+fn ssim(left: &PixelBuffer, right: &PixelBuffer, width: u32, height: u32) -> Option<f64> {
+    let dssim = dssim_core::Dssim::new();
+    let image = |buffer| {
+        let pixels = unpremultiplied(buffer, width, height);
+        dssim.create_image_rgba(&pixels, width as usize, height as usize)
+    };
+    let (left, right) = (image(left)?, image(right)?);
+    let (value, _) = dssim.compare(&left, right);
+    // DSSIM is defined as 1 / SSIM - 1
+    Some(1.0 / (1.0 + f64::from(value)))
+}
 
-        pub fn update(&mut self, state: &MouseState) {
-            let pressed = state.is_mouse_button_pressed(MouseButton::Right);
-            self.internal_update(pressed, state.x(), state.y())
-        }
+/// Compares two rendered images pixel by pixel and computes similarity metrics
+pub fn compare_buffers(left: &PixelBuffer, right: &PixelBuffer) -> Comparison {
+    compare_pixels(left, right, true)
+}
 
-        fn internal_update(&mut self, pressed: bool, x: i32, y: i32) {
-            if pressed {
-                let position = Point::new(x, y);
-                if !self.active {
-                    self.drag_start = position - self.drag;
-                    self.active = true;
-                } else {
-                    self.drag = position - self.drag_start;
+/// Pixel by pixel comparison, SSIM is computed only when `with_ssim` is set
+fn compare_pixels(left: &PixelBuffer, right: &PixelBuffer, with_ssim: bool) -> Comparison {
+    let width = cmp::max(left.width, right.width);
+    let height = cmp::max(left.height, right.height);
+
+    let mut changed_pixels = 0;
+    let mut max_difference = 0;
+    let mut squared_error: u64 = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let (l, r) = (left.pixel(x, y), right.pixel(x, y));
+            if l != r {
+                changed_pixels += 1;
+                for c in 0..4 {
+                    max_difference = cmp::max(max_difference, l[c].abs_diff(r[c]));
+                    let d = i64::from(l[c]) - i64::from(r[c]);
+                    squared_error += (d * d) as u64;
                 }
-            } else {
-                self.active = false;
             }
         }
-
-        pub fn get(&self) -> Point {
-            self.drag
-        }
     }
 
-    #[cfg(test)]
-    mod test {
-        use super::*;
+    let samples = cmp::max(u64::from(width) * u64::from(height) * 4, 1);
+    let mse = squared_error as f64 / samples as f64;
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * f64::log10(255.0 * 255.0 / mse)
+    };
+    // SSIM is the most expensive metric, skip it when there is no difference
+    let ssim = if changed_pixels == 0 {
+        Some(1.0)
+    } else if with_ssim {
+        ssim(left, right, width, height)
+    } else {
+        None
+    };
 
-        fn assert_state(d: &Drag, active: bool, start_x: i32, start_y: i32, x: i32, y: i32) {
-            assert_eq!(d.active, active);
-            assert_eq!(d.drag_start, Point::new(start_x, start_y));
-            assert_eq!(d.drag, Point::new(x, y));
-        }
+    Comparison {
+        left_size: (left.width, left.height),
+        right_size: (right.width, right.height),
+        changed_pixels,
+        max_difference,
+        psnr,
+        ssim,
+    }
+}
 
-        #[test]
-        fn test_reset() {
-            let mut d = Drag::new();
-            d.drag_start = Point::new(10, 20);
-            d.drag = Point::new(30, 40);
-            d.reset();
-            assert_state(&d, false, 0, 0, 0, 0);
-        }
+/// Luma of premultiplied RGBA pixel composited over white background
+fn luma_over_white(pixel: [u8; 4]) -> u8 {
+    let background = 255 - u32::from(pixel[3]);
+    let r = u32::from(pixel[0]) + background;
+    let g = u32::from(pixel[1]) + background;
+    let b = u32::from(pixel[2]) + background;
+    ((r * 299 + g * 587 + b * 114) / 1000) as u8
+}
 
-        #[test]
-        fn test_internal_update() {
-            let mut d = Drag::new();
-            d.internal_update(true, 10, 10);
-            // first update when pressed true does not change drag value, just toggles the state
-            assert_state(&d, true, 10, 10, 0, 0);
-            // the second update with pressed calculates new drag
-            d.internal_update(true, 15, 20);
-            assert_state(&d, true, 10, 10, 5, 10);
-            // releasing button does not reset drag, just clears the start state
-            d.internal_update(false, 20, 20);
-            assert_state(&d, false, 10, 10, 5, 10);
-            // starting all over, changing start drag position to keep continuous dragging
-            d.internal_update(true, 0, 0);
-            assert_state(&d, true, -5, -10, 5, 10);
-            d.internal_update(true, 5, 0);
-            assert_state(&d, true, -5, -10, 10, 10);
+/// Creates image with highlighted differences. Changed pixels which have more ink
+/// on the left side are considered removed (red), remaining changed pixels are
+/// considered added (green). Unchanged pixels are dimmed.
+fn highlight_differences(left: &PixelBuffer, right: &PixelBuffer) -> PixelBuffer {
+    let width = cmp::max(left.width, right.width);
+    let height = cmp::max(left.height, right.height);
+
+    let mut data = Vec::with_capacity((width * height) as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let l = left.pixel(x, y);
+            let r = right.pixel(x, y);
+            let l_luma = luma_over_white(l);
+            let color = if l == r {
+                let dimmed = 255 - (255 - l_luma) / 4;
+                [dimmed, dimmed, dimmed, 255]
+            } else if l_luma < luma_over_white(r) {
+                [255, 0, 0, 255]
+            } else {
+                [0, 192, 0, 255]
+            };
+            data.extend_from_slice(&color);
         }
     }
+
+    PixelBuffer {
+        width,
+        height,
+        data,
+    }
 }
 
-pub fn diff_files(left: &Path, right: &Path) -> Result<bool, std::io::Error> {
-    let f1 = File::open(left)?;
-    let f2 = File::open(right)?;
+/// Creates image of absolute per channel difference (like "difference" layer blend
+/// mode in image editors), identical pixels are black
+fn difference(left: &PixelBuffer, right: &PixelBuffer) -> PixelBuffer {
+    let width = cmp::max(left.width, right.width);
+    let height = cmp::max(left.height, right.height);
+
+    let mut data = Vec::with_capacity((width * height) as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let l = left.pixel(x, y);
+            let r = right.pixel(x, y);
+            data.extend_from_slice(&[
+                l[0].abs_diff(r[0]),
+                l[1].abs_diff(r[1]),
+                l[2].abs_diff(r[2]),
+                255,
+            ]);
+        }
+    }
 
-    // Check if file sizes are different
-    if f1.metadata()?.len() != f2.metadata()?.len() {
-        return Ok(false);
+    PixelBuffer {
+        width,
+        height,
+        data,
     }
+}
 
-    let mut f1 = BufReader::new(f1);
-    let mut f2 = BufReader::new(f2);
+/// Rasterizes both files in memory (without initializing SDL video) and compares
+/// them pixel by pixel. Returns comparison and warnings of both documents.
+/// When `diff_output` is set, PNG image with highlighted differences is saved there.
+/// Rendered images are kept in `cache`.
+pub fn compare<P: AsRef<Path>>(
+    left_file: P,
+    right_file: P,
+    scale: f64,
+    backends: BackendPair,
+    options: &RenderOptions,
+    cache: &RenderCache,
+    diff_output: Option<&Path>,
+) -> Result<(Comparison, WarningPair), Error> {
+    let paths = (Some(left_file.as_ref()), Some(right_file.as_ref()));
+    let (left, right) = get_pair_builders(paths, backends, options, cache)?;
+    let warnings = builder_warnings(left.as_ref(), right.as_ref());
+    let (left, right) = (left.render(scale)?, right.render(scale)?);
+
+    if let Some(path) = diff_output {
+        let diff = highlight_differences(&left, &right);
+        image::save_buffer(
+            path,
+            &diff.data,
+            diff.width,
+            diff.height,
+            image::ColorType::Rgba8,
+        )
+        .map_err(image_error)?;
+        debug!("Difference image saved to {:?}", path);
+    }
 
-    let mut buf1 = Vec::new();
-    let mut buf2 = Vec::new();
+    let comparison = compare_buffers(&left, &right);
+    debug!("{:?}", comparison);
+    Ok((comparison, warnings))
+}
 
-    // probably naive but we are not expecting large files
+/// Message bar text for pair at given position in list of `count` pairs
+fn pair_message(pair: &FilePair, index: usize, count: usize, equality: Option<Equality>) -> String {
+    let message = if count == 1 {
+        pair.label()
+    } else {
+        format!(
+            "[{}/{} {}] {}",
+            index + 1,
+            count,
+            pair.status_name(),
+            pair.label()
+        )
+    };
+    match equality {
+        Some(equality) => format!("{} [{}]", message, equality),
+        None => message,
+    }
+}
 
-    f1.read_to_end(&mut buf1)?;
-    f2.read_to_end(&mut buf2)?;
+fn builder_warnings(left: &dyn SvgTextureBuilder, right: &dyn SvgTextureBuilder) -> WarningPair {
+    (left.warnings().to_vec(), right.warnings().to_vec())
+}
 
-    for (a, b) in buf1.iter().zip(buf2.iter()) {
-        if a != b {
-            return Ok(false);
+/// Equality of files of the pair which is known without rendering,
+/// files rendered with different backends always have to be rendered
+fn pair_file_equality(pair: &FilePair, backends: BackendPair) -> Option<Equality> {
+    match pair.paths() {
+        (Some(left), Some(right)) if backends.0 == backends.1 => {
+            Equality::of_files(left, right).ok().flatten()
         }
+        _ => None,
     }
+}
 
-    Ok(true)
+/// Equality of the pair updated with result of rendering, images are compared
+/// only when the whole images were rendered
+fn rendered_equality(
+    equality: Option<Equality>,
+    rendered: &render_module::Rendered,
+    tolerance: u8,
+) -> Option<Equality> {
+    match equality {
+        Some(equality) if equality < Equality::PixelIdentical => Some(equality),
+        _ if rendered.complete() => Some(Equality::of_rendering(&rendered.comparison, tolerance)),
+        _ => equality,
+    }
 }
 
-pub fn app<P: AsRef<Path>>(
-    left_file: P,
-    right_file: P,
+/// Opens window with interactive comparison of file pairs, when more than one
+/// pair is given, keyboard can be used for switching between them
+#[allow(clippy::too_many_arguments)]
+pub fn app(
+    pairs: &[FilePair],
     scale: f64,
-    backend: SvgBackend,
+    backends: BackendPair,
+    options: &RenderOptions,
+    blink_interval: Duration,
+    cache: &RenderCache,
+    tolerance: u8,
     testing: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let texture_creator: TextureCreator<WindowContext>;
-    let left_svg = get_texture_builder(left_file.as_ref(), backend)?;
-    let right_svg = get_texture_builder(right_file.as_ref(), backend)?;
+    let mut pair_index: usize = 0;
+    let mut new_pair_index = pair_index;
+    // pair whose files are being loaded by the worker
+    let mut loading_pair: Option<usize> = None;
+    let first_pair = pairs.first().ok_or(Error::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "nothing to compare",
+    )))?;
+    let worker_stopped = || Error::Render(String::from("render worker stopped"));
+
+    // files are loaded and rendered only by the worker, layout uses their sizes
+    let mut sources = render_module::Sources::new(first_pair.paths(), backends, options, 0);
+    let mut worker = render_module::RenderWorker::new(cache);
+    worker.load(&sources, (true, true));
+    let Some(render_module::Update::Loaded(loaded)) = worker.wait() else {
+        return Err(worker_stopped());
+    };
+    let mut loaded = loaded?;
 
     let mut scale = scale;
     let mut new_scale = scale;
 
-    let left_size = left_svg.query_size(scale)?;
-    let right_size = right_svg.query_size(scale)?;
+    let left_size = loaded.left.query_size(scale)?;
+    let right_size = loaded.right.query_size(scale)?;
 
     debug!("Left SVG size {:?}", left_size.size());
     debug!("Right SVG size {:?}", right_size.size());
@@ -936,7 +4199,7 @@ pub fn app<P: AsRef<Path>>(
     let sdl_context = sdl2::init()?;
 
     let video_subsystem = sdl_context.video()?;
-    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let ttf_context = sdl2::ttf::init().map_err(|e| Error::Display(e.to_string()))?;
 
     // Load a font TODO: not sure if needed, perhaps will load with fontdb only
     let font = include_bytes!("../resources/DejaVuSansMono.ttf");
@@ -956,10 +4219,10 @@ pub fn app<P: AsRef<Path>>(
         .resizable()
         .opengl()
         .build()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| Error::Display(e.to_string()))?;
     window
         .set_minimum_size(400, 300)
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| Error::Display(e.to_string()))?;
 
     debug!(
         "Initial window size: {:?}x{:?}",
@@ -967,7 +4230,10 @@ pub fn app<P: AsRef<Path>>(
         window.size().1
     );
 
-    let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    let mut canvas = window
+        .into_canvas()
+        .build()
+        .map_err(|e| Error::Display(e.to_string()))?;
     texture_creator = canvas.texture_creator();
 
     debug!("Renderer information: {:?}", canvas.info());
@@ -982,46 +4248,66 @@ pub fn app<P: AsRef<Path>>(
             canvas.info().max_texture_height,
         )
     };
-    if left_size.size() > max_size || right_size.size() > max_size {
-        return Err(format!(
-            "ERROR: SVG file exceeds size limit of {:?}px",
-            max_size
-        ));
-    }
-
+    // images larger than maximum texture size are rendered only partially
+    let max_texture_size = max_size;
+    let max_size = MAX_IMAGE_SIZE;
     // anything smaller would be impractical to use with diff-slider
     let min_size: (u32, u32) = (100, 100);
-    if left_size.size() < min_size || right_size.size() < min_size {
-        return Err(String::from(
-            "ERROR: SVG file too small, consider using --scale option",
-        ));
-    }
+    check_size(&loaded.left, &loaded.right, scale, min_size, max_size)?;
 
     // canvas elements:
-    let left = left_svg.rasterize(&texture_creator, scale)?;
-    let right = right_svg.rasterize(&texture_creator, scale)?;
-    let mut diff = Diff::new(left, right);
+    let region = views_region(
+        &loaded.left,
+        &loaded.right,
+        scale,
+        canvas.viewport(),
+        canvas.viewport().center(),
+        false,
+        max_texture_size,
+    )?;
+    worker.request(&sources, scale, region);
+    let Some(render_module::Update::Rendered(rendered)) = worker.wait() else {
+        return Err(worker_stopped());
+    };
+    let rendered = rendered?;
+    let (mut diff, mut difference_view) = create_views(&rendered, &texture_creator)?;
     let mut workarea = CheckerBoard::new(&texture_creator, diff.size())?;
 
-    let message = format!(
-        "Left: {:?} Right: {:?}{}",
-        left_file.as_ref(),
-        right_file.as_ref(),
-        if diff_files(left_file.as_ref(), right_file.as_ref()).map_err(|e| e.to_string())? {
-            " [no differences found]"
-        } else {
-            ""
-        }
+    let mut equality = rendered_equality(
+        pair_file_equality(&pairs[pair_index], backends),
+        &rendered,
+        tolerance,
     );
-
+    let message = pair_message(&pairs[pair_index], pair_index, pairs.len(), equality);
     let mut message_bar = MessageBar::new(&message, &font, &texture_creator)?;
     let mut status_bar = StatusBar::new(&font, &texture_creator)?;
+    status_bar.set_metrics(
+        &rendered.comparison,
+        rendered.complete(),
+        font,
+        &texture_creator,
+    )?;
+    status_bar.set_warnings(&loaded.warnings, font, &texture_creator)?;
+    let mut warnings_panel = WarningsPanel::new(&loaded.warnings, font, &texture_creator)?;
 
     status_bar.reposition(canvas.viewport().bottom_left());
 
     // app logic handling:
     let mut redraw: bool = true;
+    let mut show_difference: bool = false;
+    let mut show_warnings: bool = false;
     let mut drag = drag_module::Drag::new();
+    let mut blink = blink_module::Blink::new(blink_interval);
+    let watch_pair = |pair: &FilePair| {
+        let (left, right) = pair.paths();
+        (
+            watch_module::FileWatch::new(left),
+            watch_module::FileWatch::new(right),
+        )
+    };
+    let mut watches = watch_pair(&pairs[pair_index]);
+    let mut last_watch_check = std::time::Instant::now();
+    let mut zoom_anchor: Option<ZoomAnchor> = None;
     let mut event_pump = sdl_context.event_pump()?;
 
     'running: loop {
@@ -1034,6 +4320,36 @@ pub fn app<P: AsRef<Path>>(
                     Some(sdl2::keyboard::Keycode::R) => {
                         drag.reset();
                     }
+                    Some(sdl2::keyboard::Keycode::D) => show_difference = !show_difference,
+                    Some(sdl2::keyboard::Keycode::W) => show_warnings = !show_warnings,
+                    Some(sdl2::keyboard::Keycode::H) => diff.toggle_orientation(),
+                    Some(sdl2::keyboard::Keycode::O) => diff.toggle_mode(DiffMode::OnionSkin),
+                    Some(sdl2::keyboard::Keycode::S) => diff.toggle_mode(DiffMode::SideBySide),
+                    Some(sdl2::keyboard::Keycode::B) => diff.toggle_mode(DiffMode::Blink),
+                    Some(sdl2::keyboard::Keycode::Space) => blink.toggle_pause(),
+                    Some(sdl2::keyboard::Keycode::Left) => blink.pause_on(Side::Left),
+                    Some(sdl2::keyboard::Keycode::Right) => blink.pause_on(Side::Right),
+                    Some(sdl2::keyboard::Keycode::Up) => diff.increase_opacity(),
+                    Some(sdl2::keyboard::Keycode::Down) => diff.decrease_opacity(),
+                    Some(sdl2::keyboard::Keycode::N) => {
+                        new_pair_index = cmp::min(pair_index + 1, pairs.len() - 1);
+                    }
+                    Some(sdl2::keyboard::Keycode::P) => {
+                        new_pair_index = pair_index.saturating_sub(1);
+                    }
+                    Some(sdl2::keyboard::Keycode::F) => {
+                        // fit in the area between message and status bars and center there
+                        let bars = (message_bar.size().1, status_bar.size().1);
+                        let viewport = canvas.viewport().size();
+                        let area = (viewport.0, viewport.1.saturating_sub(bars.0 + bars.1));
+                        new_scale = fit_scale(&loaded.left, &loaded.right, area)?;
+                        zoom_anchor = None;
+                        drag.set(Point::new(0, (bars.0 as i32 - bars.1 as i32) / 2));
+                    }
+                    Some(sdl2::keyboard::Keycode::Num1) => {
+                        new_scale = 1.0;
+                        zoom_anchor = Some(ZoomAnchor::Center);
+                    }
                     Some(sdl2::keyboard::Keycode::Escape) => break 'running,
                     _ => {}
                 },
@@ -1043,48 +4359,207 @@ pub fn app<P: AsRef<Path>>(
                     _ => {}
                 },
                 Event::MouseWheel { y, .. } => {
-                    new_scale = if y > 0 { scale * 2.0 } else { scale / 2.0 };
+                    let ctrl = sdl_context
+                        .keyboard()
+                        .mod_state()
+                        .intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                    if ctrl && diff.mode == DiffMode::OnionSkin {
+                        if y > 0 {
+                            diff.increase_opacity();
+                        } else {
+                            diff.decrease_opacity();
+                        }
+                    } else {
+                        let shift = sdl_context
+                            .keyboard()
+                            .mod_state()
+                            .intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                        // finer zoom steps with shift modifier
+                        let step = if shift { f64::sqrt(2.0) } else { 2.0 };
+                        new_scale = if y > 0 { scale * step } else { scale / step };
+                        zoom_anchor = Some(ZoomAnchor::Cursor);
+                    }
                 }
                 _ => {}
             }
         }
 
+        let side_by_side = diff.mode == DiffMode::SideBySide && !show_difference;
+
+        if new_pair_index != pair_index && loading_pair.is_none() {
+            sources = render_module::Sources::new(
+                pairs[new_pair_index].paths(),
+                backends,
+                options,
+                sources.generation + 1,
+            );
+            worker.load(&sources, (true, true));
+            loading_pair = Some(new_pair_index);
+        }
+
+        if loading_pair.is_none() && last_watch_check.elapsed() >= Duration::from_millis(500) {
+            last_watch_check = std::time::Instant::now();
+            let changed = (watches.0.changed(), watches.1.changed());
+            if changed.0 || changed.1 {
+                debug!("File change detected: {:?}", changed);
+                sources.generation += 1;
+                worker.load(&sources, changed);
+                loading_pair = Some(pair_index);
+            }
+        }
+
+        while let Some(update) = worker.poll() {
+            match update {
+                render_module::Update::Loaded(update) => {
+                    let index = loading_pair.take().unwrap_or(pair_index);
+                    let layout = update.and_then(|update| {
+                        check_size(&update.left, &update.right, scale, min_size, max_size)?;
+                        let region = views_region(
+                            &update.left,
+                            &update.right,
+                            scale,
+                            canvas.viewport(),
+                            canvas.viewport().center() + drag.get(),
+                            side_by_side,
+                            max_texture_size,
+                        )?;
+                        Ok((update, region))
+                    });
+                    match layout {
+                        Ok((update, region)) => {
+                            if index != pair_index {
+                                pair_index = index;
+                                debug!("Pair change: {:?}", pair_index);
+                                watches = watch_pair(&pairs[pair_index]);
+                            }
+                            loaded = update;
+                            status_bar.set_warnings(&loaded.warnings, font, &texture_creator)?;
+                            warnings_panel =
+                                WarningsPanel::new(&loaded.warnings, font, &texture_creator)?;
+                            worker.request(&sources, scale, region);
+
+                            equality = pair_file_equality(&pairs[pair_index], backends);
+                            let message =
+                                pair_message(&pairs[pair_index], pair_index, pairs.len(), equality);
+                            message_bar = MessageBar::new(&message, font, &texture_creator)?;
+                        }
+                        Err(e) if index != pair_index => {
+                            // keep showing current pair, its files get loaded
+                            // again with the next render job
+                            sources = render_module::Sources::new(
+                                pairs[pair_index].paths(),
+                                backends,
+                                options,
+                                sources.generation + 1,
+                            );
+                            let message =
+                                pair_message(&pairs[pair_index], pair_index, pairs.len(), equality);
+                            let message = format!(
+                                "{} ERROR: could not open pair {}/{}: {}",
+                                message,
+                                index + 1,
+                                pairs.len(),
+                                e
+                            );
+                            message_bar = MessageBar::new(&message, font, &texture_creator)?;
+                            new_pair_index = pair_index;
+                        }
+                        Err(e) => {
+                            equality = pair_file_equality(&pairs[pair_index], backends);
+                            let message =
+                                pair_message(&pairs[pair_index], pair_index, pairs.len(), equality);
+                            let message = format!("{} ERROR: {}", message, e);
+                            message_bar = MessageBar::new(&message, font, &texture_creator)?;
+                        }
+                    }
+                }
+                render_module::Update::Rendered(rendered) => {
+                    let views = rendered.and_then(|rendered| {
+                        status_bar.set_metrics(
+                            &rendered.comparison,
+                            rendered.complete(),
+                            font,
+                            &texture_creator,
+                        )?;
+                        let new_equality = rendered_equality(equality, &rendered, tolerance);
+                        if new_equality != equality {
+                            equality = new_equality;
+                            let message =
+                                pair_message(&pairs[pair_index], pair_index, pairs.len(), equality);
+                            message_bar = MessageBar::new(&message, font, &texture_creator)?;
+                        }
+                        create_views(&rendered, &texture_creator)
+                    });
+                    match views {
+                        Ok((new_diff, new_difference_view)) => {
+                            let previous_diff = std::mem::replace(&mut diff, new_diff);
+                            diff.copy_settings(&previous_diff);
+                            difference_view = new_difference_view;
+                            workarea.set_size(diff.size());
+                        }
+                        Err(e) => {
+                            let message =
+                                pair_message(&pairs[pair_index], pair_index, pairs.len(), equality);
+                            let message = format!("{} ERROR: {}", message, e);
+                            message_bar = MessageBar::new(&message, font, &texture_creator)?;
+                        }
+                    }
+                }
+            }
+        }
+
         if new_scale != scale {
-            /* creating new textures for new scale takes some time, disable scale changing
-             * event so rapid wheel movement does not enqueue multiple resizes while
-             * one is already ongoing */
-            event_pump.disable_event(sdl2::event::EventType::MouseWheel);
-
-            let left_size = left_svg.query_size(new_scale)?;
-            let right_size = left_svg.query_size(new_scale)?;
-            debug!("New size: {:?}", left_size);
-
-            if left_size.size() < min_size
-                || left_size.size() > max_size
-                || right_size.size() < min_size
-                || right_size.size() > max_size
-            {
+            if let Err(e) = check_size(&loaded.left, &loaded.right, new_scale, min_size, max_size) {
                 // TODO: when GUI status support added, include this message
-                println!(
-                    "ERROR: Zooming out of allowed size limit, minimum size {:?}px, maximum size {:?}px",
-                    min_size,
-                    max_size
-                );
+                println!("ERROR: Zooming out of allowed size limit: {}", e);
+                new_scale = scale;
             } else {
+                // keep anchor image point in place: vector between image center
+                // and the anchor gets scaled, so drag offset has to compensate
+                if let Some(zoom_anchor) = zoom_anchor {
+                    let viewport = canvas.viewport();
+                    let point = match zoom_anchor {
+                        ZoomAnchor::Cursor => {
+                            let mouse_state = event_pump.mouse_state();
+                            Point::new(mouse_state.x(), mouse_state.y())
+                        }
+                        ZoomAnchor::Center => viewport.center(),
+                    };
+                    let anchor = diff.image_position(point, viewport);
+                    let (width, height) = diff.size();
+                    let factor = new_scale / scale;
+                    let anchor = (
+                        f64::from(anchor.x()) - f64::from(width) / 2.0,
+                        f64::from(anchor.y()) - f64::from(height) / 2.0,
+                    );
+                    let correction = Point::new(
+                        (anchor.0 * (1.0 - factor)).round() as i32,
+                        (anchor.1 * (1.0 - factor)).round() as i32,
+                    );
+                    drag.set(drag.get() + correction);
+                }
+
+                // current textures are shown scaled until rendering at new scale is done
+                diff.rescale(new_scale / scale);
+                difference_view.rescale(new_scale / scale);
+                workarea.set_size(diff.size());
                 scale = new_scale;
                 debug!("Scale change: {:?}", scale);
 
-                // TODO: some caching could be implemented:
-                let left = left_svg.rasterize(&texture_creator, scale)?;
-                let right = right_svg.rasterize(&texture_creator, scale)?;
-
-                let left_fraction = diff.get_left_fraction();
-
-                diff = Diff::new(left, right);
-                diff.split_by_fraction(left_fraction);
-                workarea.set_size(diff.size());
+                // otherwise region gets requested when files are loaded
+                if !worker.loading() {
+                    let region = views_region(
+                        &loaded.left,
+                        &loaded.right,
+                        scale,
+                        canvas.viewport(),
+                        canvas.viewport().center() + drag.get(),
+                        side_by_side,
+                        max_texture_size,
+                    )?;
+                    worker.request(&sources, scale, region);
+                }
             }
-            event_pump.enable_event(sdl2::event::EventType::MouseWheel);
         }
 
         if redraw || testing.is_some() {
@@ -1099,22 +4574,68 @@ pub fn app<P: AsRef<Path>>(
             drag.update(&mouse_state);
             center += drag.get();
 
-            workarea.center_on(center);
-            workarea.draw(&mut canvas)?;
+            blink.update();
+            diff.blink_side = blink.side();
 
             diff.center_on(center);
+
+            // render another part of the image when panned out of rendered region
+            let (width, height) = diff.size();
+            let visible = visible_region(viewport, diff.position, side_by_side)
+                .intersection(Rect::new(0, 0, width, height));
+            if let Some(visible) = visible {
+                if !worker.pending() && !diff.region().contains_rect(visible) {
+                    let region = render_region(diff.size(), visible, max_texture_size);
+                    debug!("Region change: {:?}", region);
+                    worker.request(&sources, scale, region);
+                }
+            }
             diff.update(&mouse_state);
-            diff.draw(&mut canvas)?;
+
+            if side_by_side {
+                for (clip, position) in diff.side_by_side_layout(viewport) {
+                    canvas.set_clip_rect(clip);
+                    workarea.reposition(position);
+                    workarea.draw(&mut canvas)?;
+                }
+                canvas.set_clip_rect(None);
+            } else {
+                workarea.center_on(center);
+                workarea.draw(&mut canvas)?;
+            }
+
+            if show_difference {
+                difference_view.center_on(center);
+                difference_view.draw(&mut canvas)?;
+            } else {
+                diff.draw(&mut canvas)?;
+            }
 
             message_bar.reposition(viewport.top_left());
             message_bar.draw(&mut canvas)?;
 
+            if show_warnings {
+                let below_message = Point::new(0, message_bar.size().1 as i32);
+                warnings_panel.reposition(viewport.top_left() + below_message);
+                warnings_panel.draw(&mut canvas)?;
+            }
+
+            let cursor = diff.cursor_position(viewport);
             status_bar.update(
-                mouse_state.x() - workarea.position.x(),
-                mouse_state.y() - workarea.position.y(),
+                cursor.x(),
+                cursor.y(),
                 diff.split as i32,
                 scale,
+                match diff.mode {
+                    DiffMode::OnionSkin => Some(diff.opacity),
+                    _ => None,
+                },
+                match diff.mode {
+                    DiffMode::Blink => Some((blink.side(), blink.paused())),
+                    _ => None,
+                },
             );
+            status_bar.set_rendering(worker.pending());
             status_bar
                 .reposition(viewport.bottom_left() - Point::new(0, status_bar.size().1 as i32));
             status_bar.draw(&mut canvas)?;
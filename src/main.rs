@@ -1,9 +1,17 @@
-use lukaj::{app, SvgBackend};
+use lukaj::{
+    app, compare, diff_directories, init_logger, Equality, Error, FilePair, FontOptions, Fonts,
+    ImageRendering, InputFile, RenderCache, RenderOptions, ShapeRendering, SvgBackend,
+    TextRendering, WarningPair,
+};
 
 use clap::{ArgAction, Parser, ValueEnum};
 use log::debug;
 use std::env;
+use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
 
 #[cfg(not(any(feature = "use-rsvg", feature = "use-usvg")))]
 compile_error!("Either feature \"use-rsvg\" or \"use-usvg\" must be enabled for this crate.");
@@ -11,10 +19,19 @@ compile_error!("Either feature \"use-rsvg\" or \"use-usvg\" must be enabled for
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Files to compare
+    /// Files to compare, either paths or `<rev>:<path>` git objects.
+    /// When two directories are given, images are paired by relative path
     #[arg(required = true, num_args = 2..=2, action = ArgAction::Append)]
     file: Vec<PathBuf>,
 
+    /// Name displayed instead of the left file path
+    #[arg(long, value_name = "NAME")]
+    left_label: Option<String>,
+
+    /// Name displayed instead of the right file path
+    #[arg(long, value_name = "NAME")]
+    right_label: Option<String>,
+
     /// Sets a scaling factor
     #[arg(short, long, value_name = "VALUE")]
     scale: Option<f64>,
@@ -22,10 +39,129 @@ struct Cli {
     /// Preferred backend
     #[arg(long, value_enum, default_value_t=SvgBackend::value_variants()[0])]
     backend: SvgBackend,
+
+    /// Backend used for the left file instead of --backend
+    #[arg(long, value_enum, value_name = "BACKEND")]
+    left_backend: Option<SvgBackend>,
+
+    /// Backend used for the right file instead of --backend
+    #[arg(long, value_enum, value_name = "BACKEND")]
+    right_backend: Option<SvgBackend>,
+
+    /// Renders shapes and text without antialiasing
+    #[arg(long)]
+    no_antialiasing: bool,
+
+    /// Overrides `shape-rendering` property of all elements
+    #[arg(long, value_enum, value_name = "MODE")]
+    shape_rendering: Option<ShapeRendering>,
+
+    /// Overrides `text-rendering` property of all elements
+    #[arg(long, value_enum, value_name = "MODE")]
+    text_rendering: Option<TextRendering>,
+
+    /// Overrides `image-rendering` property of all elements
+    #[arg(long, value_enum, value_name = "MODE")]
+    image_rendering: Option<ImageRendering>,
+
+    /// CSS stylesheet applied to both documents
+    #[arg(long, value_name = "FILE")]
+    stylesheet: Option<PathBuf>,
+
+    /// Does not use fonts installed in the system (usvg-with-skia backend only)
+    #[arg(long)]
+    no_system_fonts: bool,
+
+    /// Loads fonts from given directory, can be used multiple times (usvg-with-skia backend only)
+    #[arg(long, value_name = "DIR")]
+    font_dir: Vec<PathBuf>,
+
+    /// Loads font from given file, can be used multiple times (usvg-with-skia backend only)
+    #[arg(long, value_name = "FILE")]
+    font_file: Vec<PathBuf>,
+
+    /// Font family used for generic `serif` family and text without font family
+    /// (usvg-with-skia backend only)
+    #[arg(long, value_name = "NAME")]
+    serif_family: Option<String>,
+
+    /// Font family used for generic `sans-serif` family (usvg-with-skia backend only)
+    #[arg(long, value_name = "NAME")]
+    sans_serif_family: Option<String>,
+
+    /// Font family used for generic `monospace` family (usvg-with-skia backend only)
+    #[arg(long, value_name = "NAME")]
+    monospace_family: Option<String>,
+
+    /// Sets an interval of switching images in blink mode
+    #[arg(long, value_name = "MILLISECONDS", default_value_t = 500)]
+    blink_interval: u64,
+
+    /// Memory limit for rendered images kept for reuse when zooming and comparing
+    #[arg(long, value_name = "MEGABYTES", default_value_t = 256)]
+    cache_size: usize,
+
+    /// Maximum difference of a color channel value (0-255) for rendered
+    /// images to be considered equal
+    #[arg(long, value_name = "VALUE", default_value_t = 0)]
+    tolerance: u8,
+
+    /// Compare files without opening a window, exit with 0 when files
+    /// are equal, 1 when images differ and 2-6 on error (see README for
+    /// exit statuses). Backend warnings are printed as well, rsvg-with-cairo
+    /// reports only unsupported elements (set RSVG_LOG=1 for other librsvg
+    /// diagnostics)
+    #[arg(long)]
+    compare: bool,
+
+    /// Writes PNG image with highlighted differences to given file
+    /// (implies --compare)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+fn print_warnings(warnings: &WarningPair) {
+    for warning in &warnings.0 {
+        println!("Warning (left): {}", warning);
+    }
+    for warning in &warnings.1 {
+        println!("Warning (right): {}", warning);
+    }
+}
+
+/// Exit status of comparison, files equal at any level exit with 0 as in
+/// directory mode, the level itself is printed
+fn equality_status(equality: Equality) -> ExitCode {
+    match equality {
+        Equality::Different => ExitCode::from(1),
+        _ => ExitCode::SUCCESS,
+    }
 }
 
-fn main() -> Result<(), String> {
-    env_logger::init();
+/// Exit status of failed run, 0 and 1 are reserved for comparison results
+fn report_error(e: &Error) -> ExitCode {
+    eprintln!("ERROR: {}", e);
+    ExitCode::from(match e {
+        Error::Io(_) => 2,
+        Error::Parse { .. } => 3,
+        Error::Render(_) => 4,
+        Error::SizeOutOfRange { .. } => 5,
+        Error::Display(_) => 6,
+    })
+}
+
+fn input_file(path: &Path, label: Option<String>) -> Result<InputFile, Error> {
+    let file = InputFile::new(path)?;
+    Ok(match label {
+        Some(label) => file.with_label(label),
+        None => file,
+    })
+}
+
+fn main() -> ExitCode {
+    if let Err(e) = init_logger() {
+        eprintln!("ERROR: {}", e);
+    }
     let test_tmpdir = env::var("CARGO_TARGET_TMPDIR");
     if test_tmpdir.is_ok() {
         debug!(
@@ -37,12 +173,128 @@ fn main() -> Result<(), String> {
     let cli = Cli::parse();
 
     let scale = cli.scale.unwrap_or(1.0);
-    let backend = cli.backend;
+    let backends = (
+        cli.left_backend.unwrap_or(cli.backend),
+        cli.right_backend.unwrap_or(cli.backend),
+    );
+    let tolerance = cli.tolerance;
+    let stylesheet = match cli
+        .stylesheet
+        .as_deref()
+        .map(fs::read_to_string)
+        .transpose()
+    {
+        Ok(stylesheet) => stylesheet,
+        Err(e) => return report_error(&Error::Io(e)),
+    };
+    let options = RenderOptions {
+        antialiasing: !cli.no_antialiasing,
+        shape_rendering: cli.shape_rendering,
+        text_rendering: cli.text_rendering,
+        image_rendering: cli.image_rendering,
+        stylesheet,
+        fonts: Fonts::new(FontOptions {
+            system_fonts: !cli.no_system_fonts,
+            font_dirs: cli.font_dir,
+            font_files: cli.font_file,
+            serif_family: cli.serif_family,
+            sans_serif_family: cli.sans_serif_family,
+            monospace_family: cli.monospace_family,
+        }),
+    };
+
+    let headless = cli.compare || cli.output.is_some();
+    let cache = RenderCache::new(cli.cache_size.saturating_mul(1 << 20));
+    let blink_interval = Duration::from_millis(cli.blink_interval);
 
-    let left = cli.file[0].to_owned();
-    let right = cli.file[1].to_owned();
+    let pairs = if cli.file[0].is_dir() && cli.file[1].is_dir() {
+        if cli.output.is_some() {
+            eprintln!("ERROR: --output option is not supported when comparing directories");
+            return ExitCode::from(2);
+        }
+        if cli.left_label.is_some() || cli.right_label.is_some() {
+            eprintln!("ERROR: label options are not supported when comparing directories");
+            return ExitCode::from(2);
+        }
+        let pairs = match diff_directories(
+            &cli.file[0],
+            &cli.file[1],
+            scale,
+            backends,
+            &options,
+            &cache,
+            tolerance,
+        ) {
+            Ok(pairs) => pairs,
+            Err(e) => return report_error(&e),
+        };
+        if pairs.is_empty() {
+            println!("No differences found");
+            return ExitCode::SUCCESS;
+        }
+        if headless {
+            for pair in &pairs {
+                println!("{}: {}", pair.status_name(), pair.label());
+                print_warnings(&pair.warnings);
+            }
+            return ExitCode::from(1);
+        }
+        pairs
+    } else {
+        let files = input_file(&cli.file[0], cli.left_label)
+            .and_then(|left| input_file(&cli.file[1], cli.right_label).map(|right| (left, right)));
+        let (left, right) = match files {
+            Ok(files) => files,
+            Err(e) => return report_error(&e),
+        };
 
-    app(left, right, scale, backend, test_tmpdir.ok())?;
+        if headless {
+            // same file rendered with different backends still has to be compared
+            let files = if backends.0 == backends.1 {
+                Equality::of_files(left.as_ref(), right.as_ref())
+            } else {
+                Ok(None)
+            };
+            let equality = files.map_err(Error::from).and_then(|equality| {
+                let (comparison, warnings) = compare(
+                    &left,
+                    &right,
+                    scale,
+                    backends,
+                    &options,
+                    &cache,
+                    cli.output.as_deref(),
+                )?;
+                println!("{}", comparison);
+                print_warnings(&warnings);
+                Ok(equality.unwrap_or(Equality::of_rendering(&comparison, tolerance)))
+            });
+            return match equality {
+                Ok(equality) => {
+                    println!("Result: {}", equality);
+                    equality_status(equality)
+                }
+                Err(e) => report_error(&e),
+            };
+        }
 
-    Ok(())
+        match FilePair::new(left, right) {
+            Ok(pair) => vec![pair],
+            Err(e) => return report_error(&e),
+        }
+    };
+
+    match app(
+        &pairs,
+        scale,
+        backends,
+        &options,
+        blink_interval,
+        &cache,
+        tolerance,
+        test_tmpdir.ok(),
+    ) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => report_error(&e),
+    }
 }
@@ -1,9 +1,15 @@
+use clap::ValueEnum;
 use dssim_core::{Dssim, DssimImage, Val};
 use image::imageops::FilterType;
 use image::DynamicImage;
+use lukaj::{compare_buffers, Document, RenderOptions, SvgBackend};
+#[cfg(feature = "use-usvg")]
+use lukaj::{FontOptions, Fonts};
 use rgb::*;
 use rstest::rstest;
 use std::env;
+use std::error::Error;
+use std::fs;
 use std::io;
 use std::path::Path;
 use std::process::{Child, Command, Stdio};
@@ -169,3 +175,558 @@ fn run_invalid_scale(
 
     Ok(())
 }
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn run_exceeding_texture_size(#[case] backend: String) -> Result<(), String> {
+    // image wider than the maximum texture size, rendered only partially
+    let screenshot_name = format!("{}-large-scale.bmp", backend);
+    let result = format!("{}/{}", TMPDIR, screenshot_name);
+
+    let mut command = Command::new(EXECUTABLE);
+    command
+        .env("CARGO_TARGET_TMPDIR", TMPDIR)
+        .env("TEST_OUTPUT_FILENAME", &screenshot_name)
+        .args([
+            "-s500",
+            "--backend",
+            &backend,
+            "tests/images/tinycircle01.svg",
+            "tests/images/tinycircle01.svg",
+        ]);
+
+    let wrapped = wrap_with_xvfb(&mut command).map_err(|e| e.to_string())?;
+    let output = wrapped.wait_with_output().map_err(|e| e.to_string())?;
+
+    assert!(output.status.success());
+    assert!(Path::new(&result).exists());
+
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn run_compare(
+    #[case] backend: String,
+    #[values(
+        ("arcs01.svg", "arcs01.svg", 0),
+        ("arcs01.svg", "arcs01_2.svg", 1),
+        ("arcs01.svg", "does_not_exist.svg", 2),
+        ("arcs01.png", "arcs01.png", 0),
+        ("arcs01.png", "arcs01_2.svg", 1)
+    )]
+    args: (&str, &str, i32),
+) -> Result<(), String> {
+    let files = (args.0, args.1);
+    let expected_code = args.2;
+
+    // compare mode does not open a window so it does not need xvfb
+    let output = Command::new(EXECUTABLE)
+        .args([
+            "--compare",
+            "--backend",
+            &backend,
+            &format!("tests/images/{}", files.0),
+            &format!("tests/images/{}", files.1),
+        ])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(expected_code));
+    if expected_code < 2 {
+        // similarity metrics are printed for successful comparison
+        let stdout = str::from_utf8(&output.stdout).map_err(|e| e.to_string())?;
+        assert!(stdout.contains("PSNR"));
+        assert!(stdout.contains("SSIM"));
+    }
+    Ok(())
+}
+
+const RED_SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"><rect x="4" y="4" width="8" height="8" fill="#ff0000"/></svg>"##;
+
+const SPACED_TEXT: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="16"><text x="0" y="12" font-family="monospace" font-size="12">a<tspan> b</tspan></text></svg>"##;
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn run_compare_equality(
+    #[case] backend: String,
+    #[values(
+        ("reordered", RED_SQUARE, r##"<svg height="16" width="16" xmlns="http://www.w3.org/2000/svg">
+  <!-- formatting changed -->
+  <rect height="8" width="8" y="4" x="4" fill="#ff0000"/>
+</svg>"##, "0", "files are XML equivalent"),
+        ("named-color", RED_SQUARE, r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"><rect x="4" y="4" width="8" height="8" fill="red"/></svg>"##, "0", "rendered images are identical"),
+        ("close-color", RED_SQUARE, r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"><rect x="4" y="4" width="8" height="8" fill="#fe0000"/></svg>"##, "1", "rendered images are identical within tolerance"),
+        ("close-color-strict", RED_SQUARE, r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"><rect x="4" y="4" width="8" height="8" fill="#fe0000"/></svg>"##, "0", "images differ"),
+        // whitespace in text content is significant
+        ("text-space", SPACED_TEXT, r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="16"><text x="0" y="12" font-family="monospace" font-size="12">a<tspan>b</tspan></text></svg>"##, "0", "images differ"),
+        ("text-space-reordered", SPACED_TEXT, r##"<svg height="16" width="64" xmlns="http://www.w3.org/2000/svg">
+  <text font-size="12" font-family="monospace" y="12" x="0">a<tspan> b</tspan></text>
+</svg>"##, "0", "files are XML equivalent"),
+        ("preserved-space", r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="16"><text x="0" y="12" font-family="monospace" font-size="12" xml:space="preserve">a   b</text></svg>"##, r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="16"><text x="0" y="12" font-family="monospace" font-size="12" xml:space="preserve">a b</text></svg>"##, "0", "images differ")
+    )]
+    args: (&str, &str, &str, &str, &str),
+) -> Result<(), String> {
+    let (name, left_svg, right_svg, tolerance, expected_result) = args;
+    let left = format!("{}/{}-equality-{}-left.svg", TMPDIR, backend, name);
+    let right = format!("{}/{}-equality-{}-right.svg", TMPDIR, backend, name);
+    fs::write(&left, left_svg).map_err(|e| e.to_string())?;
+    fs::write(&right, right_svg).map_err(|e| e.to_string())?;
+
+    let output = Command::new(EXECUTABLE)
+        .args([
+            "--compare",
+            "--backend",
+            &backend,
+            "--tolerance",
+            tolerance,
+            // text is rendered the same way regardless of installed fonts
+            "--font-file",
+            "resources/DejaVuSansMono.ttf",
+            &left,
+            &right,
+        ])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    // files equal at any level exit with 0, the level is printed
+    let expected_code = if expected_result == "images differ" {
+        1
+    } else {
+        0
+    };
+    assert_eq!(output.status.code(), Some(expected_code));
+    let stdout = str::from_utf8(&output.stdout).map_err(|e| e.to_string())?;
+    assert!(stdout.contains(&format!("Result: {}", expected_result)));
+    Ok(())
+}
+
+#[cfg(all(feature = "use-rsvg", feature = "use-usvg"))]
+#[rstest]
+fn run_compare_backends(
+    #[values(("rsvg-with-cairo", "usvg-with-skia"), ("usvg-with-skia", "rsvg-with-cairo"))]
+    backends: (&str, &str),
+) -> Result<(), String> {
+    let output = Command::new(EXECUTABLE)
+        .args([
+            "--compare",
+            "--left-backend",
+            backends.0,
+            "--right-backend",
+            backends.1,
+            "tests/images/arcs01.svg",
+            "tests/images/arcs01.svg",
+        ])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    // same file is not reported as identical, it has to be rendered by both backends
+    let code = output.status.code().ok_or("terminated by signal")?;
+    assert!([0, 1].contains(&code));
+    let stdout = str::from_utf8(&output.stdout).map_err(|e| e.to_string())?;
+    assert!(stdout.contains("PSNR"));
+    assert!(!stdout.contains("Result: files are identical"));
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn run_compare_output(
+    #[case] backend: String,
+    #[values(("arcs01", "arcs01", false), ("arcs01", "arcs01_2", true))] args: (&str, &str, bool),
+) -> Result<(), String> {
+    let files = (args.0, args.1);
+    let expect_changes = args.2;
+    let result = format!("{}/{}-{}-{}.png", TMPDIR, backend, files.0, files.1);
+
+    let output = Command::new(EXECUTABLE)
+        .args([
+            "--backend",
+            &backend,
+            "--output",
+            &result,
+            &format!("tests/images/{}.svg", files.0),
+            &format!("tests/images/{}.svg", files.1),
+        ])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(expect_changes as i32));
+
+    // unchanged pixels are dimmed to grayscale, changed ones are tinted
+    let img = image(&result)?.to_rgba8();
+    let tinted = img
+        .pixels()
+        .filter(|p| p[0] != p[1] || p[1] != p[2])
+        .count();
+    assert_eq!(tinted > 0, expect_changes);
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn run_compare_git_object(
+    #[case] backend: String,
+    #[values(
+        ("HEAD:tests/images/arcs01.svg", 0),
+        ("HEAD:tests/images/does_not_exist.svg", 2)
+    )]
+    args: (&str, i32),
+) -> Result<(), String> {
+    let (spec, expected_code) = args;
+
+    let output = Command::new(EXECUTABLE)
+        .args([
+            "--compare",
+            "--backend",
+            &backend,
+            spec,
+            "tests/images/arcs01.svg",
+        ])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(expected_code));
+    Ok(())
+}
+
+#[test]
+fn run_compare_drive_letter() -> Result<(), String> {
+    // missing Windows path is not mistaken for git object
+    let output = Command::new(EXECUTABLE)
+        .args([
+            "--compare",
+            "C:\\does_not_exist.svg",
+            "tests/images/arcs01.svg",
+        ])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = str::from_utf8(&output.stderr).map_err(|e| e.to_string())?;
+    assert!(!stderr.contains("git"));
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn run_compare_directories(#[case] backend: String) -> Result<(), String> {
+    let left_dir = format!("{}/{}-directories/left", TMPDIR, backend);
+    let right_dir = format!("{}/{}-directories/right", TMPDIR, backend);
+
+    let copy = |source: &str, destination: String| -> Result<(), String> {
+        let destination = Path::new(&destination);
+        fs::create_dir_all(destination.parent().unwrap()).map_err(|e| e.to_string())?;
+        fs::copy(format!("tests/images/{}", source), destination).map_err(|e| e.to_string())?;
+        Ok(())
+    };
+    copy("arcs01.svg", format!("{}/same.svg", left_dir))?;
+    copy("arcs01.svg", format!("{}/same.svg", right_dir))?;
+    copy("arcs01.svg", format!("{}/nested/modified.svg", left_dir))?;
+    copy("arcs01_2.svg", format!("{}/nested/modified.svg", right_dir))?;
+    copy("tinycircle01.svg", format!("{}/removed.svg", left_dir))?;
+    copy("tinycircle01.svg", format!("{}/added.svg", right_dir))?;
+
+    let output = Command::new(EXECUTABLE)
+        .args(["--compare", "--backend", &backend, &left_dir, &right_dir])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = str::from_utf8(&output.stdout).map_err(|e| e.to_string())?;
+    let statuses: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.split(':').next())
+        .collect();
+    assert_eq!(statuses, vec!["added", "modified", "removed"]);
+    Ok(())
+}
+
+#[rstest]
+fn run_compare_directories_label(
+    #[values("--left-label", "--right-label")] option: &str,
+) -> Result<(), String> {
+    // labels name single files, they can't be applied to directory pairs
+    let output = Command::new(EXECUTABLE)
+        .args(["--compare", option, "name", "tests/images", "tests/images"])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(2));
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn library_compare(
+    #[case] backend: String,
+    #[values(
+        ("arcs01.svg", "arcs01.svg", true),
+        ("arcs01.svg", "arcs01_2.svg", false),
+        ("arcs01.svg", "tinycircle01.svg", false)
+    )]
+    args: (&str, &str, bool),
+) -> Result<(), Box<dyn Error>> {
+    let backend = SvgBackend::from_str(&backend, false)?;
+    let left = Document::load(format!("tests/images/{}", args.0), backend)?;
+    let right = Document::load(format!("tests/images/{}", args.1), backend)?;
+
+    let left = left.render(2.0)?;
+    let right = right.render(2.0)?;
+    let comparison = compare_buffers(&left, &right);
+
+    assert_eq!(comparison.identical(), args.2);
+    assert_eq!(comparison.changed_pixels == 0, args.2);
+    assert_eq!(comparison.left_size, (left.width, left.height));
+    assert_eq!(comparison.psnr.is_infinite(), args.2);
+    let ssim = comparison.ssim.ok_or("SSIM not computed")?;
+    assert_eq!(ssim == 1.0, args.2);
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn library_render(#[case] backend: String) -> Result<(), Box<dyn Error>> {
+    let backend = SvgBackend::from_str(&backend, false)?;
+    let document = Document::load("tests/images/tinycircle01.svg", backend)?;
+
+    let size = document.size(3.0)?;
+    let image = document.render(3.0)?;
+    assert_eq!((image.width, image.height), size);
+    assert_eq!(image.data.len(), (size.0 * size.1 * 4) as usize);
+
+    // circle in the center, transparent corners
+    assert_eq!(image.pixel(0, 0), [0, 0, 0, 0]);
+    assert_eq!(image.pixel(size.0 / 2, size.1 / 2), [255, 0, 0, 255]);
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn library_render_options(
+    #[case] backend: String,
+    #[values(true, false)] antialiasing: bool,
+) -> Result<(), Box<dyn Error>> {
+    let backend = SvgBackend::from_str(&backend, false)?;
+    let options = RenderOptions {
+        antialiasing,
+        ..Default::default()
+    };
+    let document = Document::load_with_options("tests/images/tinycircle01.svg", backend, &options)?;
+    let image = document.render(3.0)?;
+
+    // circle edges are blended with transparent background only when antialiased
+    let blended = image
+        .data
+        .chunks_exact(4)
+        .any(|pixel| pixel[3] != 0 && pixel[3] != 255);
+    assert_eq!(blended, antialiasing);
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn library_render_stylesheet(#[case] backend: String) -> Result<(), Box<dyn Error>> {
+    let backend = SvgBackend::from_str(&backend, false)?;
+    let options = RenderOptions {
+        stylesheet: Some(String::from("circle { fill: lime; }")),
+        ..Default::default()
+    };
+    let document = Document::load_with_options("tests/images/tinycircle01.svg", backend, &options)?;
+    let image = document.render(3.0)?;
+
+    // stylesheet rule takes precedence over presentation attribute
+    assert_eq!(
+        image.pixel(image.width / 2, image.height / 2),
+        [0, 255, 0, 255]
+    );
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn library_render_stylesheet_precedence(#[case] backend: String) -> Result<(), Box<dyn Error>> {
+    let path = format!("{}/stylesheet-precedence-{}.svg", TMPDIR, backend);
+    fs::write(
+        &path,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+  <style>rect { fill: blue; }</style>
+  <rect id="square" width="16" height="16" fill="red"/>
+</svg>"##,
+    )?;
+    let backend = SvgBackend::from_str(&backend, false)?;
+    let options = RenderOptions {
+        stylesheet: Some(String::from("#square { fill: lime; }")),
+        ..Default::default()
+    };
+    let image = Document::load_with_options(&path, backend, &options)?.render(1.0)?;
+
+    // rules of the document win even over more specific rules of user stylesheet
+    assert_eq!(image.pixel(8, 8), [0, 0, 255, 255]);
+    Ok(())
+}
+
+#[rstest]
+#[cfg_attr(feature = "use-rsvg", case("rsvg-with-cairo"))]
+#[cfg_attr(feature = "use-usvg", case("usvg-with-skia"))]
+fn library_render_stylesheet_variables(#[case] backend: String) -> Result<(), Box<dyn Error>> {
+    // warnings are collected only with logger of the crate, which may be
+    // already installed by another test
+    let _ = lukaj::init_logger();
+    let path = format!("{}/stylesheet-variables-{}.svg", TMPDIR, backend);
+    fs::write(
+        &path,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+  <rect width="16" height="16" fill="red"/>
+</svg>"##,
+    )?;
+    let backend = SvgBackend::from_str(&backend, false)?;
+    let options = RenderOptions {
+        stylesheet: Some(String::from(
+            ":root { --color: lime; } rect { fill: var(--color); }",
+        )),
+        ..Default::default()
+    };
+    let document = Document::load_with_options(&path, backend, &options)?;
+
+    // declaration using variable is ignored, but not silently
+    assert_eq!(document.render(1.0)?.pixel(8, 8), [255, 0, 0, 255]);
+    assert!(document
+        .warnings()
+        .iter()
+        .any(|warning| warning.contains("custom properties")));
+    Ok(())
+}
+
+#[cfg(feature = "use-usvg")]
+#[rstest]
+fn library_render_fonts(#[values(true, false)] with_font: bool) -> Result<(), Box<dyn Error>> {
+    let path = format!("{}/fonts-{}.svg", TMPDIR, with_font);
+    fs::write(
+        &path,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="16">
+  <text x="0" y="12" font-family="monospace" font-size="12">lukaj</text>
+</svg>"#,
+    )?;
+    let font_files = if with_font {
+        vec!["resources/DejaVuSansMono.ttf".into()]
+    } else {
+        vec![]
+    };
+    let options = RenderOptions {
+        fonts: Fonts::new(FontOptions {
+            system_fonts: false,
+            font_files,
+            monospace_family: Some(String::from("DejaVu Sans Mono")),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let document = Document::load_with_options(&path, SvgBackend::UsvgWithSkia, &options)?;
+    let image = document.render(1.0)?;
+
+    // without system fonts text is rendered only with explicitly loaded font
+    let ink = image.data.chunks_exact(4).any(|pixel| pixel[3] != 0);
+    assert_eq!(ink, with_font);
+    Ok(())
+}
+
+#[cfg(feature = "use-usvg")]
+#[test]
+fn run_compare_warnings() -> Result<(), String> {
+    let path = format!("{}/warnings.svg", TMPDIR);
+    fs::write(
+        &path,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="16">
+  <text x="0" y="12" font-family="monospace" font-size="12">lukaj</text>
+</svg>"#,
+    )
+    .map_err(|e| e.to_string())?;
+
+    // without any fonts text can't be rendered, which is reported for both sides
+    let output = Command::new(EXECUTABLE)
+        .args([
+            "--compare",
+            "--backend",
+            "usvg-with-skia",
+            "--no-system-fonts",
+            &path,
+            &path,
+        ])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = str::from_utf8(&output.stdout).map_err(|e| e.to_string())?;
+    assert!(stdout.contains("Warning (left): No match for"));
+    assert!(stdout.contains("Warning (right): No match for"));
+    Ok(())
+}
+
+#[cfg(feature = "use-usvg")]
+#[test]
+fn run_compare_directories_warnings() -> Result<(), String> {
+    let left_dir = format!("{}/warnings-directories/left", TMPDIR);
+    let right_dir = format!("{}/warnings-directories/right", TMPDIR);
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="16">
+  <text x="0" y="12" font-family="monospace" font-size="12">lukaj</text>
+</svg>"#;
+    for dir in [&left_dir, &right_dir] {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(format!("{}/removed.svg", left_dir), svg).map_err(|e| e.to_string())?;
+    fs::write(format!("{}/added.svg", right_dir), svg).map_err(|e| e.to_string())?;
+
+    // warnings of files of added and removed pairs are printed as well
+    let output = Command::new(EXECUTABLE)
+        .args([
+            "--compare",
+            "--backend",
+            "usvg-with-skia",
+            "--no-system-fonts",
+            &left_dir,
+            &right_dir,
+        ])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = str::from_utf8(&output.stdout).map_err(|e| e.to_string())?;
+    assert!(stdout.contains("Warning (left): No match for"));
+    assert!(stdout.contains("Warning (right): No match for"));
+    Ok(())
+}
+
+#[cfg(feature = "use-rsvg")]
+#[test]
+fn run_compare_warnings_rsvg() -> Result<(), String> {
+    let path = format!("{}/warnings-rsvg.svg", TMPDIR);
+    fs::write(
+        &path,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+  <foreignObject width="16" height="16"/>
+</svg>"#,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let output = Command::new(EXECUTABLE)
+        .args(["--compare", "--backend", "rsvg-with-cairo", &path, &path])
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = str::from_utf8(&output.stdout).map_err(|e| e.to_string())?;
+    assert!(stdout.contains("Warning (left): 'foreignObject' elements are not supported"));
+    Ok(())
+}